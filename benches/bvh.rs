@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use nalgebra::Point3;
+use ray_tracer::camera::Camera;
+use ray_tracer::render::Render;
+use ray_tracer::scene_generator;
+
+const IMAGE_SIZE: usize = 16;
+
+fn linear_vs_bvh(c: &mut Criterion) {
+    let mut camera = Camera::new(Point3::new(-5.0, 0.0, 2.0));
+    camera.rotate(1.0, 0.0, 0.0);
+
+    let mut group = c.benchmark_group("spheres_demo");
+    group.sample_size(10);
+
+    for &number_of_spheres in &[2_000, 20_000, 200_000] {
+        // Keep the density of the default 200 sphere demo.
+        let half_extent = 30.0 * (number_of_spheres as f64 / 200.0).sqrt();
+        let scene = scene_generator::spheres_demo_in_area(number_of_spheres, half_extent);
+
+        group.bench_with_input(
+            BenchmarkId::new("linear", number_of_spheres),
+            &scene,
+            |b, scene| {
+                b.iter(|| {
+                    Render::new(scene, &camera, IMAGE_SIZE, IMAGE_SIZE)
+                        .without_bvh()
+                        .into_canvas()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("bvh", number_of_spheres),
+            &scene,
+            |b, scene| b.iter(|| Render::new(scene, &camera, IMAGE_SIZE, IMAGE_SIZE).into_canvas()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, linear_vs_bvh);
+criterion_main!(benches);
@@ -1,12 +1,19 @@
 use clap::App;
+use ray_tracer::canvas::ExrPrecision;
+use ray_tracer::render::Integrator;
+use ray_tracer::tone_mapping::ToneMapping;
 
 pub struct CommandLineArguments {
     pub filename: String,
-    pub output_width: usize,
-    pub output_height: usize,
-    pub antialias_factor: usize,
-    pub render_width: usize,
-    pub render_height: usize,
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    pub integrator: Integrator,
+    pub tone_mapping: ToneMapping,
+    pub exposure: f64,
+    pub exr_precision: ExrPrecision,
+    pub scene: Option<String>,
+    pub mesh: Option<String>,
 }
 
 impl CommandLineArguments {
@@ -17,20 +24,48 @@ impl CommandLineArguments {
         let antialias_factor = matches.value_of("antialias")
             .and_then(|a| a.parse::<usize>().ok())
             .unwrap_or(1);
-        let output_width = matches.value_of("width")
+        let samples = matches.value_of("spp")
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(antialias_factor * antialias_factor);
+        let width = matches.value_of("width")
             .and_then(|w| w.parse::<usize>().ok())
             .unwrap_or(800);
-        let output_height = matches.value_of("height")
+        let height = matches.value_of("height")
             .and_then(|h| h.parse::<usize>().ok())
-            .unwrap_or(output_width);
+            .unwrap_or(width);
+        let integrator = matches.value_of("integrator")
+            .and_then(|i| i.parse::<Integrator>().ok())
+            .unwrap_or(Integrator::Whitted);
+        let tone_mapping = match matches.value_of("tone_mapping")
+            .and_then(|t| t.parse::<ToneMapping>().ok())
+            .unwrap_or(ToneMapping::Clamp)
+        {
+            ToneMapping::ExtendedReinhard { white } => ToneMapping::ExtendedReinhard {
+                white: matches.value_of("white")
+                    .and_then(|w| w.parse::<f64>().ok())
+                    .unwrap_or(white),
+            },
+            operator => operator,
+        };
+        let exposure = matches.value_of("exposure")
+            .and_then(|e| e.parse::<f64>().ok())
+            .unwrap_or(0.0);
+        let exr_precision = match matches.value_of("exr_precision") {
+            Some("full") => ExrPrecision::Full,
+            _ => ExrPrecision::Half,
+        };
 
         Self {
             filename: String::from(filename),
-            output_width,
-            output_height,
-            antialias_factor,
-            render_width: output_width * antialias_factor,
-            render_height: output_height * antialias_factor,
+            width,
+            height,
+            samples,
+            integrator,
+            tone_mapping,
+            exposure,
+            exr_precision,
+            scene: matches.value_of("scene").map(String::from),
+            mesh: matches.value_of("mesh").map(String::from),
         }
     }
 }
\ No newline at end of file
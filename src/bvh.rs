@@ -0,0 +1,417 @@
+use crate::geometry::{BoundingBox, Line3d};
+
+use nalgebra::Point3;
+
+const BINS: usize = 16;
+const MAX_LEAF_SIZE: usize = 4;
+const TRAVERSAL_COST: f64 = 1.0;
+
+enum Node {
+    Leaf {
+        bounds: BoundingBox,
+        first: usize,
+        count: usize,
+    },
+    Interior {
+        bounds: BoundingBox,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Node {
+    fn bounds(&self) -> &BoundingBox {
+        match self {
+            Node::Leaf { bounds, .. } => bounds,
+            Node::Interior { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over a list of primitives, built using the
+/// surface area heuristic. Primitives without a bounding box are tested
+/// against every ray.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(bounds: &[Option<BoundingBox>]) -> Self {
+        let (bounded, unbounded) = Self::split_unbounded(bounds);
+        let boxes: Vec<BoundingBox> = bounded.iter().map(|&(_, bounds)| bounds).collect();
+        let mut order: Vec<usize> = (0..boxes.len()).collect();
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            indices: Vec::new(),
+            unbounded,
+        };
+
+        if !boxes.is_empty() {
+            bvh.build(&boxes, &mut order, 0);
+        }
+        bvh.indices = order.iter().map(|&i| bounded[i].0).collect();
+
+        bvh
+    }
+
+    /// Puts every primitive into a single leaf, which makes queries
+    /// equivalent to a linear scan.
+    pub fn flat(bounds: &[Option<BoundingBox>]) -> Self {
+        let (bounded, unbounded) = Self::split_unbounded(bounds);
+        let mut nodes = Vec::new();
+        if !bounded.is_empty() {
+            nodes.push(Node::Leaf {
+                bounds: bounded
+                    .iter()
+                    .fold(BoundingBox::empty(), |all, (_, bounds)| all.union(bounds)),
+                first: 0,
+                count: bounded.len(),
+            });
+        }
+
+        Bvh {
+            nodes,
+            indices: bounded.iter().map(|&(index, _)| index).collect(),
+            unbounded,
+        }
+    }
+
+    fn split_unbounded(bounds: &[Option<BoundingBox>]) -> (Vec<(usize, BoundingBox)>, Vec<usize>) {
+        let bounded = bounds
+            .iter()
+            .enumerate()
+            .filter_map(|(index, bounds)| bounds.map(|bounds| (index, bounds)))
+            .collect();
+        let unbounded = bounds
+            .iter()
+            .enumerate()
+            .filter(|(_, bounds)| bounds.is_none())
+            .map(|(index, _)| index)
+            .collect();
+        (bounded, unbounded)
+    }
+
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        if self.unbounded.is_empty() {
+            self.nodes.first().map(|node| *node.bounds())
+        } else {
+            None
+        }
+    }
+
+    fn build(&mut self, boxes: &[BoundingBox], order: &mut [usize], first: usize) -> usize {
+        let bounds = order
+            .iter()
+            .fold(BoundingBox::empty(), |all, &i| all.union(&boxes[i]));
+        let node_index = self.nodes.len();
+        self.nodes.push(Node::Leaf {
+            bounds,
+            first,
+            count: order.len(),
+        });
+
+        if order.len() <= MAX_LEAF_SIZE {
+            return node_index;
+        }
+
+        let split = match Self::find_split(boxes, order, &bounds) {
+            Some(split) => split,
+            None => return node_index,
+        };
+
+        let (left_order, right_order) = order.split_at_mut(split);
+        let left = self.build(boxes, left_order, first);
+        let right = self.build(boxes, right_order, first + split);
+        self.nodes[node_index] = Node::Interior {
+            bounds,
+            left,
+            right,
+        };
+
+        node_index
+    }
+
+    /// Partitions `order` along the cheapest binned SAH split and returns the
+    /// size of the left part, or `None` if a leaf is cheaper.
+    fn find_split(boxes: &[BoundingBox], order: &mut [usize], bounds: &BoundingBox) -> Option<usize> {
+        let centroid_bounds = order
+            .iter()
+            .fold(BoundingBox::empty(), |all, &i| all.grow(&boxes[i].centroid()));
+        let axis = centroid_bounds.longest_axis();
+        let low = centroid_bounds.min()[axis];
+        let span = centroid_bounds.max()[axis] - low;
+        if span <= 0.0 {
+            return None;
+        }
+
+        let bin_of = |i: usize| {
+            let offset = (boxes[i].centroid()[axis] - low) / span;
+            ((offset * BINS as f64) as usize).min(BINS - 1)
+        };
+
+        let mut bin_bounds = [BoundingBox::empty(); BINS];
+        let mut bin_counts = [0usize; BINS];
+        for &i in order.iter() {
+            let bin = bin_of(i);
+            bin_bounds[bin] = bin_bounds[bin].union(&boxes[i]);
+            bin_counts[bin] += 1;
+        }
+
+        let mut right_areas = [0.0; BINS];
+        let mut right_counts = [0usize; BINS];
+        let mut accumulated = BoundingBox::empty();
+        let mut count = 0;
+        for bin in (1..BINS).rev() {
+            accumulated = accumulated.union(&bin_bounds[bin]);
+            count += bin_counts[bin];
+            right_areas[bin] = accumulated.surface_area();
+            right_counts[bin] = count;
+        }
+
+        let mut best: Option<(usize, f64)> = None;
+        let mut accumulated = BoundingBox::empty();
+        let mut count = 0;
+        for split in 1..BINS {
+            accumulated = accumulated.union(&bin_bounds[split - 1]);
+            count += bin_counts[split - 1];
+            if count == 0 || right_counts[split] == 0 {
+                continue;
+            }
+
+            let cost = accumulated.surface_area() * count as f64
+                + right_areas[split] * right_counts[split] as f64;
+            let better = match best {
+                Some((_, best_cost)) => cost < best_cost,
+                None => true,
+            };
+            if better {
+                best = Some((split, cost));
+            }
+        }
+
+        let (split_bin, cost) = best?;
+        let leaf_cost = order.len() as f64;
+        if TRAVERSAL_COST + cost / bounds.surface_area() >= leaf_cost {
+            return None;
+        }
+
+        order.sort_unstable_by_key(|&i| bin_of(i) >= split_bin);
+        Some(order.iter().filter(|&&i| bin_of(i) < split_bin).count())
+    }
+
+    /// Finds the closest hit along the ray. `hit` is called for every candidate
+    /// primitive and returns the distance along the ray of its nearest valid
+    /// intersection; subtrees further away than the best hit so far are skipped.
+    pub fn nearest<T>(
+        &self,
+        ray: &Line3d,
+        mut hit: impl FnMut(usize) -> Option<(f64, T)>,
+    ) -> Option<(f64, T)> {
+        let mut best: Option<(f64, T)> = None;
+        let mut consider = |index: usize, best: &mut Option<(f64, T)>| {
+            if let Some((distance, value)) = hit(index) {
+                let better = match best {
+                    Some((best_distance, _)) => distance < *best_distance,
+                    None => true,
+                };
+                if better {
+                    *best = Some((distance, value));
+                }
+            }
+        };
+
+        for &index in &self.unbounded {
+            consider(index, &mut best);
+        }
+
+        let mut stack = Vec::new();
+        if let Some(root) = self.nodes.first() {
+            if let Some((near, _)) = root.bounds().intersect(ray, 0.0, f64::INFINITY) {
+                stack.push((0, near));
+            }
+        }
+
+        while let Some((node_index, near)) = stack.pop() {
+            let limit = best.as_ref().map_or(f64::INFINITY, |(distance, _)| *distance);
+            if near > limit {
+                continue;
+            }
+
+            match &self.nodes[node_index] {
+                Node::Leaf { first, count, .. } => {
+                    for &index in &self.indices[*first..*first + *count] {
+                        consider(index, &mut best);
+                    }
+                }
+                Node::Interior { left, right, .. } => {
+                    let left_hit = self.nodes[*left].bounds().intersect(ray, 0.0, limit);
+                    let right_hit = self.nodes[*right].bounds().intersect(ray, 0.0, limit);
+                    match (left_hit, right_hit) {
+                        (Some((left_near, _)), Some((right_near, _))) => {
+                            // Visit the nearer child first.
+                            if left_near <= right_near {
+                                stack.push((*right, right_near));
+                                stack.push((*left, left_near));
+                            } else {
+                                stack.push((*left, left_near));
+                                stack.push((*right, right_near));
+                            }
+                        }
+                        (Some((left_near, _)), None) => stack.push((*left, left_near)),
+                        (None, Some((right_near, _))) => stack.push((*right, right_near)),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Calls `visit` for every primitive whose bounding box is crossed by the
+    /// ray between `min_distance` and `max_distance`, stopping early once it
+    /// returns `true`. Returns whether any call returned `true`.
+    pub fn any(
+        &self,
+        ray: &Line3d,
+        min_distance: f64,
+        max_distance: f64,
+        mut visit: impl FnMut(usize) -> bool,
+    ) -> bool {
+        if self.unbounded.iter().any(|&index| visit(index)) {
+            return true;
+        }
+
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.bounds().intersect(ray, min_distance, max_distance).is_none() {
+                continue;
+            }
+
+            match node {
+                Node::Leaf { first, count, .. } => {
+                    if self.indices[*first..*first + *count].iter().any(|&index| visit(index)) {
+                        return true;
+                    }
+                }
+                Node::Interior { left, right, .. } => {
+                    stack.push(*right);
+                    stack.push(*left);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Calls `visit` for the primitives of every leaf whose bounding box,
+    /// grown by `margin` on every side, contains the point, which includes
+    /// all primitives whose own boxes contain it.
+    pub fn containing(&self, point: &Point3<f64>, margin: f64, mut visit: impl FnMut(usize)) {
+        self.unbounded.iter().for_each(|&index| visit(index));
+
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node.bounds().contains(point, margin) {
+                continue;
+            }
+
+            match node {
+                Node::Leaf { first, count, .. } => {
+                    self.indices[*first..*first + *count].iter().for_each(|&index| visit(index));
+                }
+                Node::Interior { left, right, .. } => {
+                    stack.push(*right);
+                    stack.push(*left);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+
+    fn unit_boxes(count: usize) -> Vec<Option<BoundingBox>> {
+        (0..count)
+            .map(|i| {
+                let x = 2.0 * i as f64;
+                Some(BoundingBox::new(
+                    Point3::new(x, 0.0, 0.0),
+                    Point3::new(x + 1.0, 1.0, 1.0),
+                ))
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn nearest_finds_closest_box() {
+        let boxes = unit_boxes(100);
+        let bvh = Bvh::new(&boxes);
+        let ray = Line3d::new(Point3::new(-1.0, 0.5, 0.5), Vector3::x_axis());
+
+        let nearest = bvh.nearest(&ray, |i| {
+            boxes[i]
+                .unwrap()
+                .intersect(&ray, 0.0, f64::INFINITY)
+                .map(|(near, _)| (near, i))
+        });
+
+        assert_eq!(nearest, Some((1.0, 0)));
+    }
+
+    #[test]
+    pub fn any_visits_only_crossed_boxes() {
+        let boxes = unit_boxes(100);
+        let bvh = Bvh::new(&boxes);
+        let ray = Line3d::new(Point3::new(100.5, 0.5, -1.0), Vector3::z_axis());
+
+        let mut visited = Vec::new();
+        bvh.any(&ray, 0.0, f64::INFINITY, |i| {
+            visited.push(i);
+            false
+        });
+
+        assert!(visited.contains(&50));
+        assert!(visited.len() < 10);
+    }
+
+    #[test]
+    pub fn containing_visits_boxes_around_point() {
+        let mut boxes = unit_boxes(100);
+        boxes.push(None);
+        let bvh = Bvh::new(&boxes);
+
+        let mut visited = Vec::new();
+        bvh.containing(&Point3::new(41.0, 0.5, 1.0), 1e-9, |i| visited.push(i));
+
+        assert!(visited.contains(&20));
+        assert!(visited.contains(&100));
+        assert!(visited.len() < 10);
+    }
+
+    #[test]
+    pub fn unbounded_primitives_always_visited() {
+        let mut boxes = unit_boxes(10);
+        boxes.push(None);
+        let bvh = Bvh::new(&boxes);
+        let ray = Line3d::new(Point3::new(0.0, 10.0, 0.0), Vector3::z_axis());
+
+        assert!(bvh.any(&ray, 0.0, f64::INFINITY, |i| i == 10));
+        assert_eq!(bvh.bounds(), None);
+    }
+}
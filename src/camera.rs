@@ -1,4 +1,5 @@
 use crate::geometry::{utils::*, Line3d};
+use crate::sampling;
 
 use nalgebra::{Point3, Rotation3, Unit, Vector3};
 use rayon::prelude::*;
@@ -28,11 +29,15 @@ impl Camera {
         &self.center
     }
 
+    /// Rays through every pixel of an image of the given size. A single
+    /// sample goes through the pixel center; multiple samples are jittered
+    /// across the pixel area.
     pub fn rays(
         &self,
         width: usize,
         height: usize,
-    ) -> impl ParallelIterator<Item = (usize, usize, Line3d)> {
+        samples: usize,
+    ) -> impl ParallelIterator<Item = (usize, usize, Vec<Line3d>)> {
         let left_unit = self.up.cross(&self.direction);
         let aspect_ratio = (width as f64) / (height as f64);
 
@@ -48,14 +53,22 @@ impl Camera {
             .into_par_iter()
             .flat_map(move |x| (0..height).into_par_iter().map(move |y| (x, y)))
             .map(move |(x, y)| {
-                let pixel_center =
-                    canvas_corner + pixel_right * (x as f64 + 0.5) + pixel_down * (y as f64 + 0.5);
-
-                (
-                    x,
-                    y,
-                    Line3d::new(pixel_center, camera_center.unit_to(&pixel_center)),
-                )
+                let offsets = if samples <= 1 {
+                    vec![(0.5, 0.5)]
+                } else {
+                    sampling::stratified_square(samples, &mut rand::thread_rng())
+                };
+
+                let rays = offsets
+                    .into_iter()
+                    .map(|(dx, dy)| {
+                        let position =
+                            canvas_corner + pixel_right * (x as f64 + dx) + pixel_down * (y as f64 + dy);
+                        Line3d::new(position, camera_center.unit_to(&position))
+                    })
+                    .collect();
+
+                (x, y, rays)
             })
     }
 }
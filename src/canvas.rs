@@ -2,21 +2,30 @@ use std::fs;
 use std::io;
 use std::vec::Vec;
 
-use crate::color::Color;
+use crate::hdr;
 use crate::progress_bar::ProgressBar;
+use crate::radiance::Radiance;
+use crate::tone_mapping::ToneMapper;
 
+use exr::prelude::f16;
 use png::{BitDepth, ColorType, Encoder};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExrPrecision {
+    Half,
+    Full,
+}
+
 pub struct Canvas {
     width: usize,
     height: usize,
-    pixels: Vec<Color>,
+    pixels: Vec<Radiance>,
 }
 
 impl Canvas {
-    pub fn new(width: usize, height: usize, color: Color) -> Canvas {
+    pub fn new(width: usize, height: usize, radiance: Radiance) -> Canvas {
         let mut pixels = Vec::with_capacity(width * height);
-        pixels.resize(width * height, color);
+        pixels.resize(width * height, radiance);
 
         Canvas {
             width,
@@ -25,17 +34,17 @@ impl Canvas {
         }
     }
 
-    pub fn get(&self, x: usize, y: usize) -> Option<&Color> {
+    pub fn get(&self, x: usize, y: usize) -> Option<&Radiance> {
         self.pixels.get(y * self.width + x)
     }
 
-    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Color> {
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Radiance> {
         self.pixels.get_mut(y * self.width + x)
     }
 
-    pub fn save_png(&self, filename: &str) {
+    pub fn save_png(&self, filename: &str, tone_mapper: &ToneMapper) {
         let file = fs::File::create(filename).expect("Could not open file");
-        let ref mut w = io::BufWriter::new(file);
+        let w = &mut io::BufWriter::new(file);
         let mut encoder = Encoder::new(w, self.width as u32, self.height as u32);
 
         encoder.set_color(ColorType::RGB);
@@ -47,39 +56,35 @@ impl Canvas {
             .pixels
             .iter()
             .inspect(|_| progress_bar.step().print())
-            .flat_map(|pixel| vec![pixel.r, pixel.g, pixel.b])
+            .map(|pixel| tone_mapper.to_color(pixel))
+            .flat_map(|color| vec![color.r, color.g, color.b])
             .collect();
 
         writer.write_image_data(data.as_slice()).unwrap();
     }
 
-    pub fn downsample(self, factor: usize) -> Self {
-        let new_width = self.width / factor;
-        let new_height = self.height / factor;
-        let mut downsampled = Self::new(new_width, new_height, Color::gray(0));
-
-        for (out_x, out_y) in iproduct!(0..new_width, 0..new_height) {
-            let mut r = 0.0;
-            let mut g = 0.0;
-            let mut b = 0.0;
-
-            for (in_x, in_y) in iproduct!(0..factor, 0..factor) {
-                if let Some(color) = self.get(out_x * factor + in_x, out_y * factor + in_y) {
-                    r += (color.r as f64).powi(2);
-                    g += (color.g as f64).powi(2);
-                    b += (color.b as f64).powi(2);
-                }
-            }
-
-            if let Some(pixel) = downsampled.get_mut(out_x, out_y) {
-                *pixel = Color {
-                    r: (r / (factor as f64).powi(2)).sqrt() as u8,
-                    g: (g / (factor as f64).powi(2)).sqrt() as u8,
-                    b: (b / (factor as f64).powi(2)).sqrt() as u8,
-                };
-            }
-        }
+    /// Saves linear radiance, without tone mapping, as an OpenEXR image.
+    pub fn save_exr(&self, filename: &str, precision: ExrPrecision) {
+        let pixel = |x, y| *self.get(x, y).expect("Pixel out of bounds");
+        let result = match precision {
+            ExrPrecision::Half => exr::prelude::write_rgb_file(filename, self.width, self.height, |x, y| {
+                let p = pixel(x, y);
+                (f16::from_f64(p.r), f16::from_f64(p.g), f16::from_f64(p.b))
+            }),
+            ExrPrecision::Full => exr::prelude::write_rgb_file(filename, self.width, self.height, |x, y| {
+                let p = pixel(x, y);
+                (p.r as f32, p.g as f32, p.b as f32)
+            }),
+        };
+
+        result.expect("Could not write file");
+    }
+
+    /// Saves linear radiance, without tone mapping, as a Radiance HDR image.
+    pub fn save_hdr(&self, filename: &str) {
+        let file = fs::File::create(filename).expect("Could not open file");
+        let w = &mut io::BufWriter::new(file);
 
-        downsampled
+        hdr::write(w, self.width, self.height, &self.pixels).expect("Could not write file");
     }
 }
@@ -36,31 +36,4 @@ impl Color {
             b: rng.gen::<u8>(),
         }
     }
-
-    pub fn dim(&self, illumination: f64) -> Self {
-        if illumination <= 0.0 {
-            Color::gray(0)
-        } else if illumination >= 1.0 {
-            self.clone()
-        } else {
-            Color::rgb(
-                (f64::from(self.r) * illumination) as u8,
-                (f64::from(self.g) * illumination) as u8,
-                (f64::from(self.b) * illumination) as u8,
-            )
-        }
-    }
-
-    fn blend_component(a: u8, b: u8, ratio: f64) -> u8 {
-        ((a as f64).powi(2) + (b as f64).powi(2)*ratio).sqrt().min(255.0) as u8
-    }
-
-    pub fn blend(&self, other: &Color, ratio: f64) -> Color {
-        let ratio = ratio.max(0.0).min(1.0);
-        Color {
-            r: Self::blend_component(self.r, other.r, ratio),
-            g: Self::blend_component(self.g, other.g, ratio),
-            b: Self::blend_component(self.b, other.b, ratio),
-        }
-    }
 }
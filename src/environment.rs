@@ -0,0 +1,226 @@
+use std::f64::consts::PI;
+use std::io;
+
+use crate::hdr;
+use crate::light::LightSample;
+use crate::radiance::Radiance;
+use crate::sampling::PiecewiseConstant;
+
+use nalgebra::{Unit, Vector3};
+use rand::Rng;
+
+/// Light arriving from every direction, stored as an equirectangular image
+/// with +Z at the top row. Directions are importance sampled by luminance.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Radiance>,
+    /// Rotation around the Z axis, in radians.
+    rotation: f64,
+    /// Number of shadow rays cast towards the map by the Whitted integrator.
+    samples: usize,
+    rows: PiecewiseConstant,
+    columns: Vec<PiecewiseConstant>,
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<Radiance>) -> Self {
+        // Rows near the poles cover a smaller solid angle.
+        let row_weight = |y: usize| (PI * (y as f64 + 0.5) / height as f64).sin();
+        let columns: Vec<PiecewiseConstant> = pixels
+            .chunks(width)
+            .map(|row| {
+                let weights: Vec<f64> = row.iter().map(|pixel| pixel.luminance()).collect();
+                PiecewiseConstant::new(&weights)
+            })
+            .collect();
+        let row_weights: Vec<f64> = pixels
+            .chunks(width)
+            .enumerate()
+            .map(|(y, row)| {
+                let luminance: f64 = row.iter().map(|pixel| pixel.luminance().max(0.0)).sum();
+                luminance * row_weight(y)
+            })
+            .collect();
+
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+            samples: 16,
+            rows: PiecewiseConstant::new(&row_weights),
+            columns,
+        }
+    }
+
+    /// Loads a map from a Radiance `.hdr` file.
+    pub fn load(filename: &str) -> io::Result<Self> {
+        let (width, height, pixels) = hdr::load(filename)?;
+        if width == 0 || height == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty image"));
+        }
+        Ok(EnvironmentMap::new(width, height, pixels))
+    }
+
+    /// Scales every pixel by `intensity`.
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        for pixel in &mut self.pixels {
+            *pixel = *pixel * intensity;
+        }
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f64) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Position within the image, both coordinates in `[0, 1)`.
+    fn image_position(&self, direction: &Vector3<f64>) -> (f64, f64) {
+        let direction = direction.normalize();
+        let phi = direction.y.atan2(direction.x) - self.rotation;
+        let u = (phi / (2.0 * PI)).rem_euclid(1.0);
+        let v = direction.z.clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    fn pixel(&self, (u, v): (f64, f64)) -> (usize, usize) {
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        (x, y)
+    }
+
+    fn direction(&self, u: f64, v: f64) -> Unit<Vector3<f64>> {
+        let phi = 2.0 * PI * u + self.rotation;
+        let theta = PI * v;
+        Unit::new_normalize(Vector3::new(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        ))
+    }
+
+    /// Radiance arriving from the given direction.
+    pub fn radiance(&self, direction: &Vector3<f64>) -> Radiance {
+        let (x, y) = self.pixel(self.image_position(direction));
+        self.pixels[y * self.width + x]
+    }
+
+    /// Solid angle density with which `sample` picks the given direction.
+    pub fn pdf(&self, direction: &Vector3<f64>) -> f64 {
+        let (u, v) = self.image_position(direction);
+        let (x, y) = self.pixel((u, v));
+        self.density(x, y, v)
+    }
+
+    /// Converts the density over the image to one over solid angle.
+    fn density(&self, x: usize, y: usize, v: f64) -> f64 {
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.rows.density(y) * self.columns[y].density(x) / (2.0 * PI * PI * sin_theta)
+    }
+
+    /// Picks a direction with probability roughly proportional to the
+    /// radiance arriving from it. Returns the direction, its radiance and the
+    /// solid angle density.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> (Unit<Vector3<f64>>, Radiance, f64) {
+        let (y, row_offset) = self.rows.sample(rng.gen());
+        let (x, column_offset) = self.columns[y].sample(rng.gen());
+        let u = (x as f64 + column_offset) / self.width as f64;
+        let v = (y as f64 + row_offset) / self.height as f64;
+
+        (self.direction(u, v), self.pixels[y * self.width + x], self.density(x, y, v))
+    }
+
+    /// Treats the map as `count` infinitely distant lights whose radiance
+    /// averages to the light arriving from the whole environment.
+    pub fn light_samples<R: Rng>(&self, count: usize, rng: &mut R) -> Vec<LightSample> {
+        (0..count)
+            .map(|_| self.sample(rng))
+            .filter(|&(_, _, pdf)| pdf > 0.0)
+            .map(|(direction, radiance, pdf)| LightSample {
+                direction,
+                distance: f64::INFINITY,
+                radiance: radiance / (pdf * count as f64),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn with_bright_spot() -> EnvironmentMap {
+        let mut pixels = vec![Radiance::gray(0.1); 16 * 8];
+        pixels[2 * 16 + 4] = Radiance::gray(100.0);
+        EnvironmentMap::new(16, 8, pixels)
+    }
+
+    #[test]
+    pub fn up_is_top_row() {
+        let mut pixels = vec![Radiance::black(); 4 * 2];
+        pixels[0] = Radiance::gray(1.0);
+        let map = EnvironmentMap::new(4, 2, pixels);
+
+        assert_eq!(map.radiance(&Vector3::new(1.0, -0.1, 1.0)), Radiance::black());
+        assert_eq!(map.radiance(&Vector3::new(1.0, 0.1, 1.0)), Radiance::gray(1.0));
+        assert_eq!(map.radiance(&Vector3::new(1.0, 0.1, -1.0)), Radiance::black());
+    }
+
+    #[test]
+    pub fn sampled_directions_round_trip() {
+        let map = with_bright_spot().with_rotation(1.0);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..100 {
+            let (direction, radiance, pdf) = map.sample(&mut rng);
+
+            assert_eq!(map.radiance(&direction), radiance);
+            assert!((map.pdf(&direction) - pdf).abs() < 1e-9 * pdf, "{} {}", map.pdf(&direction), pdf);
+        }
+    }
+
+    #[test]
+    pub fn samples_favor_bright_regions() {
+        let map = with_bright_spot();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let bright = (0..1000)
+            .filter(|_| map.sample(&mut rng).1 == Radiance::gray(100.0))
+            .count();
+
+        assert!(bright > 900, "{}", bright);
+    }
+
+    #[test]
+    pub fn light_samples_estimate_irradiance() {
+        let map = EnvironmentMap::new(8, 4, vec![Radiance::gray(1.0); 8 * 4]);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        // A uniform environment of unit radiance delivers an irradiance of pi
+        // onto an upward facing surface.
+        let irradiance: f64 = map
+            .light_samples(100_000, &mut rng)
+            .iter()
+            .map(|sample| sample.radiance.r * sample.direction.z.max(0.0))
+            .sum();
+
+        assert!((irradiance - PI).abs() < 0.05, "{}", irradiance);
+    }
+}
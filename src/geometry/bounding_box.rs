@@ -0,0 +1,145 @@
+use crate::geometry::Line3d;
+
+use nalgebra::{Point3, Vector3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    min: Point3<f64>,
+    max: Point3<f64>,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point3<f64>, max: Point3<f64>) -> Self {
+        BoundingBox { min, max }
+    }
+
+    pub fn empty() -> Self {
+        BoundingBox {
+            min: Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn around(points: &[Point3<f64>]) -> Self {
+        points
+            .iter()
+            .fold(Self::empty(), |bounds, point| bounds.grow(point))
+    }
+
+    pub fn min(&self) -> &Point3<f64> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Point3<f64> {
+        &self.max
+    }
+
+    pub fn grow(&self, point: &Point3<f64>) -> Self {
+        BoundingBox {
+            min: self.min.inf(point),
+            max: self.max.sup(point),
+        }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        BoundingBox {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn centroid(&self) -> Point3<f64> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    pub fn extent(&self) -> Vector3<f64> {
+        (self.max - self.min).sup(&Vector3::zeros())
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let extent = self.extent();
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        self.extent().imax()
+    }
+
+    /// Whether the point lies inside the box grown by `margin` on every side.
+    pub fn contains(&self, point: &Point3<f64>, margin: f64) -> bool {
+        (0..3).all(|axis| {
+            point[axis] >= self.min[axis] - margin && point[axis] <= self.max[axis] + margin
+        })
+    }
+
+    /// Returns the range of distances along the ray for which it is inside
+    /// the box, clipped to `[min_distance, max_distance]`.
+    pub fn intersect(&self, ray: &Line3d, min_distance: f64, max_distance: f64) -> Option<(f64, f64)> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let mut near = min_distance;
+        let mut far = max_distance;
+
+        for axis in 0..3 {
+            let inverse = 1.0 / direction[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inverse;
+            let mut t1 = (self.max[axis] - origin[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            // `max` and `min` ignore NaNs produced by rays lying in a slab's plane.
+            near = near.max(t0);
+            far = far.min(t1);
+            if near > far {
+                return None;
+            }
+        }
+
+        Some((near, far))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Unit;
+
+    fn unit_cube() -> BoundingBox {
+        BoundingBox::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    pub fn intersect_hit() {
+        let ray = Line3d::new(Point3::new(-1.0, 0.5, 0.5), Vector3::x_axis());
+
+        assert_eq!(
+            unit_cube().intersect(&ray, f64::NEG_INFINITY, f64::INFINITY),
+            Some((1.0, 2.0))
+        );
+    }
+
+    #[test]
+    pub fn intersect_miss() {
+        let ray = Line3d::new(
+            Point3::new(-1.0, 0.5, 0.5),
+            Unit::new_normalize(Vector3::new(1.0, 2.0, 0.0)),
+        );
+
+        assert_eq!(unit_cube().intersect(&ray, f64::NEG_INFINITY, f64::INFINITY), None);
+    }
+
+    #[test]
+    pub fn intersect_clipped_behind_origin() {
+        let ray = Line3d::new(Point3::new(2.0, 0.5, 0.5), Vector3::x_axis());
+
+        assert_eq!(unit_cube().intersect(&ray, 0.0, f64::INFINITY), None);
+    }
+
+    #[test]
+    pub fn surface_area_of_union() {
+        let other = BoundingBox::new(Point3::new(1.0, 0.0, 0.0), Point3::new(2.0, 1.0, 1.0));
+
+        assert_eq!(unit_cube().union(&other).surface_area(), 10.0);
+    }
+}
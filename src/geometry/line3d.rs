@@ -15,7 +15,7 @@ impl Line3d {
 
     pub fn between(a: &Point3<f64>, b: &Point3<f64>) -> Line3d {
         Line3d {
-            origin: a.clone(),
+            origin: *a,
             direction: a.unit_to(b),
         }
     }
@@ -1,4 +1,8 @@
+pub use self::bounding_box::BoundingBox;
 pub use self::line3d::Line3d;
+pub use self::plane3d::Plane3d;
 
 pub mod utils;
-mod line3d;
\ No newline at end of file
+mod bounding_box;
+mod line3d;
+mod plane3d;
@@ -0,0 +1,99 @@
+use crate::geometry::Line3d;
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Plane through `origin` with a frame of two in-plane axes, along which
+/// planar coordinates are measured.
+#[derive(Clone, Copy)]
+pub struct Plane3d {
+    origin: Point3<f64>,
+    normal: Unit<Vector3<f64>>,
+    tangent: Unit<Vector3<f64>>,
+    bitangent: Unit<Vector3<f64>>,
+}
+
+impl Plane3d {
+    /// Plane whose bitangent points as close to +Z as possible, so that
+    /// textures stand upright on vertical planes. Horizontal planes take +X
+    /// as their tangent.
+    pub fn new(origin: Point3<f64>, normal: Unit<Vector3<f64>>) -> Plane3d {
+        let tangent = Vector3::z()
+            .cross(&normal)
+            .try_normalize(1e-9)
+            .unwrap_or_else(Vector3::x);
+        Plane3d {
+            origin,
+            normal,
+            tangent: Unit::new_unchecked(tangent),
+            bitangent: Unit::new_normalize(normal.cross(&tangent)),
+        }
+    }
+
+    pub fn origin(&self) -> Point3<f64> {
+        self.origin
+    }
+
+    pub fn normal(&self) -> Unit<Vector3<f64>> {
+        self.normal
+    }
+
+    pub fn tangent(&self) -> Unit<Vector3<f64>> {
+        self.tangent
+    }
+
+    pub fn bitangent(&self) -> Unit<Vector3<f64>> {
+        self.bitangent
+    }
+
+    /// Distance along the ray to the plane, or `None` if the ray is parallel
+    /// to it.
+    pub fn intersect(&self, ray: &Line3d) -> Option<f64> {
+        let denominator = ray.direction().dot(&self.normal);
+        if denominator == 0.0 {
+            return None;
+        }
+        Some((self.origin - ray.origin()).dot(&self.normal) / denominator)
+    }
+
+    /// Coordinates of the projection of a point along the tangent and the
+    /// bitangent, relative to the origin.
+    pub fn coordinates(&self, point: &Point3<f64>) -> Point2<f64> {
+        let offset = point - self.origin;
+        Point2::new(offset.dot(&self.tangent), offset.dot(&self.bitangent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn horizontal_plane_follows_x_and_y() {
+        let plane = Plane3d::new(Point3::new(0.0, 0.0, 1.0), Vector3::z_axis());
+
+        assert_eq!(plane.tangent(), Vector3::x_axis());
+        assert_eq!(plane.bitangent(), Vector3::y_axis());
+        assert_eq!(plane.coordinates(&Point3::new(2.0, 3.0, 5.0)), Point2::new(2.0, 3.0));
+    }
+
+    #[test]
+    pub fn vertical_plane_is_upright() {
+        let plane = Plane3d::new(Point3::origin(), -Vector3::y_axis());
+
+        assert_eq!(plane.bitangent(), Vector3::z_axis());
+        assert_eq!(plane.tangent().cross(&plane.bitangent()), plane.normal().into_inner());
+    }
+
+    #[test]
+    pub fn intersect_from_either_side() {
+        let plane = Plane3d::new(Point3::new(0.0, 0.0, 1.0), Vector3::z_axis());
+
+        let below = Line3d::new(Point3::new(1.0, 1.0, -1.0), Vector3::z_axis());
+        let above = Line3d::new(Point3::new(1.0, 1.0, 4.0), -Vector3::z_axis());
+        let parallel = Line3d::new(Point3::origin(), Vector3::x_axis());
+
+        assert_eq!(plane.intersect(&below), Some(2.0));
+        assert_eq!(plane.intersect(&above), Some(3.0));
+        assert_eq!(plane.intersect(&parallel), None);
+    }
+}
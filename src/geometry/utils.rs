@@ -2,11 +2,26 @@ use nalgebra::{Point3, Vector3, Unit};
 
 pub trait VectorUtils {
     fn reflect(&self, normal: &Vector3<f64>) -> Vector3<f64>;
+    fn refract(&self, normal: &Vector3<f64>, eta: f64) -> Option<Vector3<f64>>;
 }
 
 impl VectorUtils for Vector3<f64> {
     fn reflect(&self, normal: &Vector3<f64>) -> Vector3<f64> {
-        self - 2.0 * normal * self.dot(&normal)
+        self - 2.0 * normal * self.dot(normal)
+    }
+
+    /// Refracts a unit vector passing through a surface with the given unit
+    /// normal, where `eta` is the ratio of refractive indices (from / to).
+    /// Returns `None` on total internal reflection.
+    fn refract(&self, normal: &Vector3<f64>, eta: f64) -> Option<Vector3<f64>> {
+        let normal = if self.dot(normal) > 0.0 { -normal } else { *normal };
+        let cos_incident = -self.dot(&normal);
+        let sin2_transmitted = eta.powi(2) * (1.0 - cos_incident.powi(2));
+        if sin2_transmitted > 1.0 {
+            return None;
+        }
+
+        Some(eta * self + (eta * cos_incident - (1.0 - sin2_transmitted).sqrt()) * normal)
     }
 }
 
@@ -23,4 +38,39 @@ impl PointUtils for Point3<f64> {
     fn distance_to(self, point: &Point3<f64>) -> f64 {
         (self - point).norm()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn refract_straight_through() {
+        let direction = Vector3::new(0.0, 0.0, -1.0);
+
+        let refracted = direction.refract(&Vector3::z(), 1.0 / 1.5).unwrap();
+
+        assert!((refracted - direction).norm() < 1e-12);
+    }
+
+    #[test]
+    pub fn refract_obeys_snell_law() {
+        let direction = Vector3::new(1.0, 0.0, -1.0).normalize();
+        let eta = 1.0 / 1.5;
+
+        let refracted = direction.refract(&Vector3::z(), eta).unwrap();
+
+        let sin_incident = direction.x;
+        let sin_transmitted = refracted.x;
+        assert!((sin_incident * eta - sin_transmitted).abs() < 1e-12);
+        assert!((refracted.norm() - 1.0).abs() < 1e-12);
+        assert!(refracted.z < 0.0);
+    }
+
+    #[test]
+    pub fn refract_total_internal_reflection() {
+        let direction = Vector3::new(1.0, 0.0, 1.0).normalize();
+
+        assert_eq!(direction.refract(&Vector3::z(), 1.5), None);
+    }
+}
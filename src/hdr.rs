@@ -0,0 +1,248 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::radiance::Radiance;
+
+/// Packs radiance into the shared-exponent RGBE representation used by
+/// Radiance `.hdr` files.
+pub fn to_rgbe(radiance: &Radiance) -> [u8; 4] {
+    let r = radiance.r.max(0.0);
+    let g = radiance.g.max(0.0);
+    let b = radiance.b.max(0.0);
+    let max = r.max(g).max(b);
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2.0f64.powi(exponent);
+    let mantissa = |value: f64| (value * scale).min(255.0) as u8;
+    [mantissa(r), mantissa(g), mantissa(b), (exponent + 128) as u8]
+}
+
+pub fn from_rgbe([r, g, b, e]: [u8; 4]) -> Radiance {
+    if e == 0 {
+        return Radiance::black();
+    }
+
+    // Decode to the middle of each mantissa's quantization step.
+    let scale = 2.0f64.powi(i32::from(e) - 128 - 8);
+    Radiance::new(
+        (f64::from(r) + 0.5) * scale,
+        (f64::from(g) + 0.5) * scale,
+        (f64::from(b) + 0.5) * scale,
+    )
+}
+
+/// Writes pixels, given row by row from the top, as an uncompressed
+/// Radiance RGBE image.
+pub fn write<W: Write>(
+    writer: &mut W,
+    width: usize,
+    height: usize,
+    pixels: &[Radiance],
+) -> io::Result<()> {
+    write!(writer, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)?;
+
+    let data: Vec<u8> = pixels.iter().flat_map(to_rgbe).collect();
+    writer.write_all(&data)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn load(filename: &str) -> io::Result<(usize, usize, Vec<Radiance>)> {
+    read(&mut BufReader::new(File::open(filename)?))
+}
+
+/// Reads a Radiance RGBE image stored top to bottom, either flat or with
+/// run-length encoded scanlines. Returns its width, height and pixels row by
+/// row from the top.
+pub fn read<R: BufRead>(reader: &mut R) -> io::Result<(usize, usize, Vec<Radiance>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !matches!(line.trim(), "#?RADIANCE" | "#?RGBE") {
+        return Err(invalid("not a Radiance image"));
+    }
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("missing image size"));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid("unsupported pixel format"));
+        }
+    }
+
+    line.clear();
+    reader.read_line(&mut line)?;
+    let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (height.parse(), width.parse()),
+        _ => return Err(invalid("unsupported image orientation")),
+    };
+    let (height, width): (usize, usize) = match (height, width) {
+        (Ok(height), Ok(width)) => (height, width),
+        _ => return Err(invalid("invalid image size")),
+    };
+
+    let size = width
+        .checked_mul(height)
+        .ok_or_else(|| invalid("invalid image size"))?;
+    let mut pixels = Vec::with_capacity(size);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        read_scanline(reader, &mut scanline)?;
+        pixels.extend(scanline.iter().map(|&rgbe| from_rgbe(rgbe)));
+    }
+
+    Ok((width, height, pixels))
+}
+
+fn read_scanline<R: Read>(reader: &mut R, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let mut start = [0u8; 4];
+    reader.read_exact(&mut start)?;
+
+    let width = scanline.len();
+    let encoded = start[0] == 2 && start[1] == 2 && start[2] & 0x80 == 0;
+    if !(8..0x8000).contains(&width) || !encoded {
+        scanline[0] = start;
+        for pixel in scanline[1..].iter_mut() {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+
+    if (usize::from(start[2]) << 8 | usize::from(start[3])) != width {
+        return Err(invalid("wrong scanline width"));
+    }
+
+    // Each channel is stored separately as runs of equal bytes and
+    // literal sequences.
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut byte = [0u8; 1];
+            reader.read_exact(&mut byte)?;
+            let (count, run) = if byte[0] > 128 {
+                (usize::from(byte[0] - 128), true)
+            } else {
+                (usize::from(byte[0]), false)
+            };
+            if count == 0 || x + count > width {
+                return Err(invalid("bad scanline data"));
+            }
+
+            if run {
+                reader.read_exact(&mut byte)?;
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = byte[0];
+                }
+            } else {
+                for pixel in &mut scanline[x..x + count] {
+                    reader.read_exact(&mut byte)?;
+                    pixel[channel] = byte[0];
+                }
+            }
+            x += count;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn rgbe_round_trip() {
+        let radiance = Radiance::new(0.25, 3.0, 1000.0);
+
+        let result = from_rgbe(to_rgbe(&radiance));
+
+        for (expected, actual) in [(0.25, result.r), (3.0, result.g), (1000.0, result.b)] {
+            // The mantissa of each channel is relative to the largest one.
+            assert!((expected - actual).abs() <= 1000.0 / 256.0, "{} {}", expected, actual);
+        }
+    }
+
+    #[test]
+    pub fn rgbe_of_black() {
+        assert_eq!(to_rgbe(&Radiance::black()), [0, 0, 0, 0]);
+        assert_eq!(from_rgbe([0, 0, 0, 0]), Radiance::black());
+    }
+
+    #[test]
+    pub fn write_header_and_pixels() {
+        let mut output = Vec::new();
+
+        write(&mut output, 2, 1, &[Radiance::gray(1.0), Radiance::gray(0.5)]).unwrap();
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n";
+        assert_eq!(&output[..header.len()], &header[..]);
+        assert_eq!(&output[header.len()..], &[128, 128, 128, 129, 128, 128, 128, 128]);
+    }
+
+    #[test]
+    pub fn read_flat_image() {
+        let pixels = [Radiance::gray(1.0), Radiance::new(0.5, 2.0, 0.0)];
+        let mut file = Vec::new();
+        write(&mut file, 1, 2, &pixels).unwrap();
+
+        let (width, height, result) = read(&mut &file[..]).unwrap();
+
+        assert_eq!((width, height), (1, 2));
+        assert_eq!(result, pixels.iter().map(|p| from_rgbe(to_rgbe(p))).collect::<Vec<_>>());
+    }
+
+    #[test]
+    pub fn read_run_length_encoded_scanline() {
+        let mut file = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        file.extend(&[2, 2, 0, 8]);
+        // Red: a run of eight, green: eight literals, blue: two runs,
+        // exponent: a run of eight.
+        file.extend(&[136, 128]);
+        file.extend(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        file.extend(&[132, 0, 132, 255]);
+        file.extend(&[136, 129]);
+
+        let (width, height, pixels) = read(&mut &file[..]).unwrap();
+
+        assert_eq!((width, height), (8, 1));
+        assert_eq!(pixels[0], from_rgbe([128, 0, 0, 129]));
+        assert_eq!(pixels[7], from_rgbe([128, 112, 255, 129]));
+    }
+
+    #[test]
+    pub fn read_rejects_unknown_orientation() {
+        let file = b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0".to_vec();
+
+        let error = read(&mut &file[..]).unwrap_err();
+
+        assert_eq!(error.to_string(), "unsupported image orientation");
+    }
+
+    #[test]
+    pub fn read_rejects_missing_magic() {
+        let file = b"P6\n1 1\n255\n\0\0\0".to_vec();
+
+        let error = read(&mut &file[..]).unwrap_err();
+
+        assert_eq!(error.to_string(), "not a Radiance image");
+    }
+
+    #[test]
+    pub fn read_rejects_overflowing_size() {
+        let file = format!("#?RADIANCE\n\n-Y {} +X 2\n", usize::MAX).into_bytes();
+
+        let error = read(&mut &file[..]).unwrap_err();
+
+        assert_eq!(error.to_string(), "invalid image size");
+    }
+}
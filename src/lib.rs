@@ -0,0 +1,24 @@
+#[macro_use]
+extern crate itertools;
+
+pub mod bvh;
+pub mod camera;
+pub mod canvas;
+pub mod color;
+pub mod environment;
+pub mod geometry;
+pub mod hdr;
+pub mod light;
+pub mod math;
+pub mod obj;
+pub mod progress_bar;
+pub mod radiance;
+pub mod render;
+pub mod sampling;
+pub mod scene;
+pub mod scene_file;
+pub mod scene_generator;
+pub mod shapes;
+pub mod sky;
+pub mod textures;
+pub mod tone_mapping;
@@ -1,12 +1,380 @@
-use nalgebra::Point3;
+use crate::geometry::utils::*;
+use crate::radiance::Radiance;
+use crate::sampling;
+
+use nalgebra::{Point3, Unit, Vector3};
+use rand::Rng;
+use std::f64::consts::PI;
+
+pub enum LightShape {
+    Point,
+    /// Parallelogram spanned by two edge vectors around the center. Emits
+    /// towards `u × v`.
+    Rectangle {
+        u: Vector3<f64>,
+        v: Vector3<f64>,
+    },
+    /// Emits towards `normal`.
+    Disk {
+        normal: Unit<Vector3<f64>>,
+        radius: f64,
+    },
+    Sphere {
+        radius: f64,
+    },
+    /// Infinitely distant light shining along `direction`.
+    Directional {
+        direction: Unit<Vector3<f64>>,
+    },
+}
+
+/// How the light reaching a point decreases with its distance from the light.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attenuation {
+    /// Constant illumination regardless of distance.
+    None,
+    /// Physically based falloff of a point source.
+    InverseSquare,
+    /// `1 / (constant + linear * d + quadratic * d²)`.
+    Polynomial {
+        constant: f64,
+        linear: f64,
+        quadratic: f64,
+    },
+}
+
+impl Attenuation {
+    pub fn factor(&self, distance: f64) -> f64 {
+        let denominator = match *self {
+            Attenuation::None => return 1.0,
+            Attenuation::InverseSquare => distance.powi(2),
+            Attenuation::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            } => constant + linear * distance + quadratic * distance.powi(2),
+        };
+
+        if denominator > 0.0 {
+            1.0 / denominator
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Angular profile restricting a light to a cone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spotlight {
+    pub direction: Unit<Vector3<f64>>,
+    /// Half-angle of the cone, in radians.
+    pub angle: f64,
+    /// Fraction of the cone, measured inwards from its edge, over which the
+    /// light fades out.
+    pub edge: f64,
+    /// Exponent of the cosine falloff away from the axis.
+    pub falloff: f64,
+}
+
+impl Spotlight {
+    pub fn new(direction: Unit<Vector3<f64>>, angle: f64) -> Self {
+        Spotlight {
+            direction,
+            angle,
+            edge: 0.0,
+            falloff: 0.0,
+        }
+    }
+
+    pub fn with_edge(mut self, edge: f64) -> Self {
+        self.edge = edge.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: f64) -> Self {
+        self.falloff = falloff.max(0.0);
+        self
+    }
+
+    /// Fraction of the light emitted along the given direction.
+    pub fn factor(&self, direction: &Vector3<f64>) -> f64 {
+        let cos = self.direction.dot(direction);
+        let angle = cos.clamp(-1.0, 1.0).acos();
+        if angle >= self.angle {
+            return 0.0;
+        }
+
+        let inner = self.angle * (1.0 - self.edge);
+        let edge = if angle <= inner {
+            1.0
+        } else {
+            let t = (self.angle - angle) / (self.angle - inner);
+            t * t * (3.0 - 2.0 * t)
+        };
+        edge * cos.max(0.0).powf(self.falloff)
+    }
+}
+
+/// Light arriving at a point from a single sample of a light.
+pub struct LightSample {
+    /// Direction from the illuminated point towards the light.
+    pub direction: Unit<Vector3<f64>>,
+    /// Distance to the light, infinite for directional lights.
+    pub distance: f64,
+    /// Radiance arriving along `direction`, before accounting for the
+    /// orientation of the illuminated surface.
+    pub radiance: Radiance,
+}
 
 pub struct Light {
+    /// Position of the light, ignored by directional lights.
     pub center: Point3<f64>,
     pub intensity: f64,
+    /// Linear RGB tint, scaled by `intensity`.
+    pub color: Radiance,
+    pub attenuation: Attenuation,
+    pub shape: LightShape,
+    pub spotlight: Option<Spotlight>,
+    /// Number of shadow rays cast towards an area light.
+    pub samples: usize,
 }
 
 impl Light {
     pub fn new(center: Point3<f64>, intensity: f64) -> Self {
-        Light { center, intensity }
+        Light {
+            center,
+            intensity,
+            color: Radiance::gray(1.0),
+            attenuation: Attenuation::InverseSquare,
+            shape: LightShape::Point,
+            spotlight: None,
+            samples: 1,
+        }
+    }
+
+    /// Infinitely distant light, such as the sun, shining along `direction`.
+    pub fn directional(direction: Unit<Vector3<f64>>, intensity: f64) -> Self {
+        Light::new(Point3::origin(), intensity).with_shape(LightShape::Directional { direction }, 1)
+    }
+
+    pub fn with_color(mut self, color: Radiance) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    pub fn with_shape(mut self, shape: LightShape, samples: usize) -> Self {
+        self.shape = shape;
+        self.samples = samples.max(1);
+        self
+    }
+
+    pub fn with_spotlight(mut self, spotlight: Spotlight) -> Self {
+        self.spotlight = Some(spotlight);
+        self
+    }
+
+    /// Samples the light as seen from the given point. Samples of area lights
+    /// are spread across the light's surface and share its power.
+    pub fn sample<R: Rng>(&self, point: &Point3<f64>, rng: &mut R) -> Vec<LightSample> {
+        if let LightShape::Directional { direction } = self.shape {
+            return vec![LightSample {
+                direction: -direction,
+                distance: f64::INFINITY,
+                radiance: self.color * self.intensity,
+            }];
+        }
+
+        self.sample_points(point, rng)
+            .into_iter()
+            .map(|(light_point, weight)| {
+                let emitted = light_point.unit_to(point);
+                let spot = self
+                    .spotlight
+                    .map_or(1.0, |spotlight| spotlight.factor(&emitted));
+                let distance = light_point.distance_to(point);
+                let factor = weight * spot * self.attenuation.factor(distance);
+                LightSample {
+                    direction: -emitted,
+                    distance,
+                    radiance: self.color * (self.intensity * factor),
+                }
+            })
+            .collect()
+    }
+
+    /// Points on the light from which the given position is illuminated,
+    /// each with the fraction of the light's intensity it emits towards it.
+    fn sample_points<R: Rng>(&self, towards: &Point3<f64>, rng: &mut R) -> Vec<(Point3<f64>, f64)> {
+        let weight = 1.0 / self.samples as f64;
+        let emitting = |point: Point3<f64>, normal: &Vector3<f64>| {
+            let cos = normal.dot(&point.unit_to(towards)).max(0.0);
+            (point, weight * cos)
+        };
+
+        match &self.shape {
+            LightShape::Point | LightShape::Directional { .. } => vec![(self.center, 1.0)],
+            LightShape::Rectangle { u, v } => {
+                let normal = u.cross(v).normalize();
+                sampling::stratified_square(self.samples, rng)
+                    .into_iter()
+                    .map(|(s, t)| emitting(self.center + u * (s - 0.5) + v * (t - 0.5), &normal))
+                    .collect()
+            }
+            LightShape::Disk { normal, radius } => sampling::stratified_square(self.samples, rng)
+                .into_iter()
+                .map(|(s, t)| emitting(Self::on_disk(&self.center, normal, *radius, s, t), normal))
+                .collect(),
+            LightShape::Sphere { radius } => {
+                // Only the silhouette matters for visibility, so sample the
+                // disk facing the illuminated point.
+                let normal = self.center.unit_to(towards);
+                sampling::stratified_square(self.samples, rng)
+                    .into_iter()
+                    .map(|(s, t)| (Self::on_disk(&self.center, &normal, *radius, s, t), weight))
+                    .collect()
+            }
+        }
+    }
+
+    /// Maps a point of the unit square uniformly onto a disk.
+    fn on_disk(
+        center: &Point3<f64>,
+        normal: &Unit<Vector3<f64>>,
+        radius: f64,
+        s: f64,
+        t: f64,
+    ) -> Point3<f64> {
+        let (tangent, bitangent) = sampling::orthonormal_basis(normal);
+        let distance = radius * s.sqrt();
+        let angle = 2.0 * PI * t;
+        center + tangent * (distance * angle.cos()) + bitangent * (distance * angle.sin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    pub fn inverse_square_falloff() {
+        let light = Light::new(Point3::origin(), 8.0)
+            .with_color(Radiance::new(1.0, 0.5, 0.0))
+            .with_attenuation(Attenuation::InverseSquare);
+
+        let samples = light.sample(&Point3::new(0.0, 2.0, 0.0), &mut StdRng::seed_from_u64(1));
+
+        assert_eq!(samples[0].radiance, Radiance::new(2.0, 1.0, 0.0));
+        assert_eq!(samples[0].distance, 2.0);
+    }
+
+    #[test]
+    pub fn polynomial_attenuation() {
+        let attenuation = Attenuation::Polynomial {
+            constant: 1.0,
+            linear: 0.5,
+            quadratic: 0.25,
+        };
+
+        assert_eq!(attenuation.factor(0.0), 1.0);
+        assert_eq!(attenuation.factor(2.0), 1.0 / 3.0);
+        assert_eq!(Attenuation::None.factor(100.0), 1.0);
+    }
+
+    #[test]
+    pub fn directional_light_is_infinitely_distant() {
+        let light = Light::directional(-Vector3::z_axis(), 2.0)
+            .with_attenuation(Attenuation::InverseSquare);
+
+        let samples = light.sample(&Point3::new(3.0, 1.0, 0.0), &mut StdRng::seed_from_u64(1));
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].direction, Vector3::z_axis());
+        assert_eq!(samples[0].distance, f64::INFINITY);
+        assert_eq!(samples[0].radiance, Radiance::gray(2.0));
+    }
+
+    #[test]
+    pub fn spotlight_cone() {
+        let spotlight = Spotlight::new(-Vector3::z_axis(), PI / 4.0).with_edge(0.5);
+
+        assert_eq!(spotlight.factor(&-Vector3::z()), 1.0);
+        assert_eq!(spotlight.factor(&Vector3::new(1.0, 0.0, -1.0).normalize()), 0.0);
+        let soft = spotlight.factor(&Vector3::new(0.5, 0.0, -1.0).normalize());
+        assert!(soft > 0.0 && soft < 1.0, "{}", soft);
+        assert_eq!(spotlight.factor(&Vector3::z()), 0.0);
+    }
+
+    #[test]
+    pub fn spotlight_falloff() {
+        let spotlight = Spotlight::new(-Vector3::z_axis(), PI / 2.0).with_falloff(2.0);
+
+        let factor = spotlight.factor(&Vector3::new(1.0, 0.0, -1.0).normalize());
+
+        assert!((factor - 0.5).abs() < 1e-9, "{}", factor);
+    }
+
+    #[test]
+    pub fn point_light_has_single_sample() {
+        let light = Light::new(Point3::new(1.0, 2.0, 3.0), 1.0);
+
+        let samples = light.sample_points(&Point3::origin(), &mut StdRng::seed_from_u64(1));
+
+        assert_eq!(samples, vec![(Point3::new(1.0, 2.0, 3.0), 1.0)]);
+    }
+
+    #[test]
+    pub fn rectangle_samples_lie_on_rectangle() {
+        let light = Light::new(Point3::new(0.0, 0.0, 5.0), 1.0).with_shape(
+            LightShape::Rectangle {
+                u: Vector3::new(2.0, 0.0, 0.0),
+                v: Vector3::new(0.0, -1.0, 0.0),
+            },
+            16,
+        );
+
+        let samples = light.sample_points(&Point3::origin(), &mut StdRng::seed_from_u64(1));
+
+        assert_eq!(samples.len(), 16);
+        for (point, weight) in samples {
+            assert_eq!(point.z, 5.0);
+            assert!(point.x.abs() <= 1.0 && point.y.abs() <= 0.5);
+            assert!(weight > 0.0 && weight <= 1.0 / 16.0);
+        }
+    }
+
+    #[test]
+    pub fn disk_does_not_emit_backwards() {
+        let light = Light::new(Point3::new(0.0, 0.0, 5.0), 1.0).with_shape(
+            LightShape::Disk {
+                normal: Vector3::z_axis(),
+                radius: 1.0,
+            },
+            4,
+        );
+
+        let samples = light.sample_points(&Point3::origin(), &mut StdRng::seed_from_u64(1));
+
+        assert!(samples.iter().all(|&(_, weight)| weight == 0.0));
+    }
+
+    #[test]
+    pub fn sphere_samples_face_illuminated_point() {
+        let light = Light::new(Point3::new(0.0, 0.0, 5.0), 1.0)
+            .with_shape(LightShape::Sphere { radius: 0.5 }, 8);
+
+        let samples = light.sample_points(&Point3::origin(), &mut StdRng::seed_from_u64(1));
+
+        for (point, weight) in samples {
+            assert_eq!(point.z, 5.0);
+            assert!((point - Point3::new(0.0, 0.0, 5.0)).norm() <= 0.5);
+            assert_eq!(weight, 1.0 / 8.0);
+        }
     }
 }
@@ -1,36 +1,65 @@
 #[macro_use]
 extern crate clap;
-#[macro_use]
-extern crate itertools;
 
 mod args;
-mod camera;
-mod canvas;
-mod color;
-mod geometry;
-mod light;
-mod math;
-mod progress_bar;
-mod render;
-mod scene;
-mod scene_generator;
-mod shapes;
+
+use std::path::Path;
+use std::process;
 
 use args::CommandLineArguments;
-use camera::Camera;
 use nalgebra::Point3;
-use render::Render;
+use ray_tracer::camera::Camera;
+use ray_tracer::color::Color;
+use ray_tracer::obj;
+use ray_tracer::render::Render;
+use ray_tracer::scene::Scene;
+use ray_tracer::scene_file;
+use ray_tracer::scene_generator;
+use ray_tracer::shapes::Material;
+use ray_tracer::tone_mapping::ToneMapper;
 
-fn main() {
+fn demo_scene() -> (Scene, Camera) {
     let mut camera = Camera::new(Point3::new(-5.0, 0.0, 2.0));
     camera.rotate(1.0, 0.0, 0.0);
 
-    let scene = scene_generator::spheres_demo(200);
+    (scene_generator::spheres_demo(200), camera)
+}
+
+fn main() {
     let args = CommandLineArguments::read();
 
-    let canvas = Render::new(&scene, &camera, args.render_width, args.render_height)
-        .into_canvas()
-        .downsample(args.antialias_factor);
+    let (mut scene, camera) = match &args.scene {
+        Some(filename) => scene_file::load(filename).unwrap_or_else(|error| {
+            eprintln!("{}", error);
+            process::exit(1);
+        }),
+        None => demo_scene(),
+    };
+
+    if let Some(filename) = &args.mesh {
+        match obj::load(filename, Color::gray(200), Material::default()) {
+            Ok(mesh) => {
+                scene.add_shape(Box::new(mesh));
+            }
+            Err(error) => {
+                eprintln!("Could not load {}: {}", filename, error);
+                process::exit(1);
+            }
+        }
+    }
+
+    let canvas = Render::new(&scene, &camera, args.width, args.height)
+        .with_integrator(args.integrator)
+        .with_samples(args.samples)
+        .into_canvas();
 
-    canvas.save_png(&args.filename);
+    let extension = Path::new(&args.filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("exr") => canvas.save_exr(&args.filename, args.exr_precision),
+        Some("hdr") => canvas.save_hdr(&args.filename),
+        _ => canvas.save_png(&args.filename, &ToneMapper::new(args.tone_mapping, args.exposure)),
+    }
 }
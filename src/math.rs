@@ -1,3 +1,9 @@
+/// Values of a polynomial within this many rounding errors of its evaluation
+/// are treated as zero.
+const ROUNDING_ERRORS: f64 = 64.0;
+const MAX_ITERATIONS: usize = 200;
+
+/// Polynomial with real coefficients, ordered from the constant term up.
 pub struct Polynomial {
     coefficients: Vec<f64>,
 }
@@ -7,29 +13,172 @@ impl Polynomial {
         Polynomial { coefficients }
     }
 
+    pub fn evaluate(&self, x: f64) -> f64 {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |value, coefficient| value * x + coefficient)
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        Polynomial::new(
+            self.coefficients
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(power, coefficient)| power as f64 * coefficient)
+                .collect(),
+        )
+    }
+
+    /// Bound on the error of `evaluate` at `x` caused by rounding.
+    fn rounding_error(&self, x: f64) -> f64 {
+        let magnitude = self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |value, coefficient| value * x.abs() + coefficient.abs());
+        ROUNDING_ERRORS * f64::EPSILON * magnitude
+    }
+
     fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
         let delta = b * b - 4.0 * a * c;
         if delta == 0.0 {
             vec!(-b / (2.0 * a))
         } else if delta > 0.0 {
-            vec!((-b - delta.sqrt()) / (2.0 * a), (-b + delta.sqrt()) / (2.0 * a))
+            // Avoids cancellation between -b and the square root.
+            let q = -0.5 * (b + b.signum() * delta.sqrt());
+            let (first, second) = (q / a, c / q);
+            vec!(first.min(second), first.max(second))
         } else {
             Vec::new()
         }
     }
 
-    pub fn into_solutions(self) -> Vec<f64> {
-        if self.coefficients.len() == 3 {
-            return Self::solve_quadratic(self.coefficients[2], self.coefficients[1], self.coefficients[0]);
+    /// Real roots of polynomials of degree three and above. The polynomial
+    /// is monotonic between consecutive roots of its derivative, so each of
+    /// those intervals holds at most one root, found by bisection sped up
+    /// with Newton's method. Extrema touching zero are double roots.
+    fn solve_by_extrema(&self) -> Vec<f64> {
+        let degree = self.coefficients.len() - 1;
+        let leading = self.coefficients[degree];
+        // Cauchy's bound on the magnitude of the roots.
+        let bound = 1.0
+            + self.coefficients[..degree]
+                .iter()
+                .map(|coefficient| (coefficient / leading).abs())
+                .fold(0.0, f64::max);
+
+        let mut points = vec![-bound];
+        points.extend(
+            self.derivative()
+                .into_solutions()
+                .into_iter()
+                .filter(|x| x.abs() < bound),
+        );
+        points.push(bound);
+
+        let is_zero = |x: f64| self.evaluate(x).abs() <= self.rounding_error(x);
+        let mut roots: Vec<f64> = Vec::new();
+        // Whether the previous extremum touched zero, in which case the next
+        // one doing so belongs to the same multiple root.
+        let mut touching = false;
+        for window in points.windows(2) {
+            let (low, high) = (window[0], window[1]);
+            if is_zero(low) {
+                if !touching {
+                    roots.push(low);
+                }
+                touching = true;
+            } else {
+                touching = false;
+                if !is_zero(high) && self.evaluate(low).signum() != self.evaluate(high).signum() {
+                    roots.push(self.refine(low, high));
+                }
+            }
+        }
+
+        roots
+    }
+
+    /// Root within an interval over which the polynomial changes sign once.
+    fn refine(&self, mut low: f64, mut high: f64) -> f64 {
+        let derivative = self.derivative();
+        let rising = self.evaluate(high) > 0.0;
+        let mut x = 0.5 * (low + high);
+
+        for _ in 0..MAX_ITERATIONS {
+            let value = self.evaluate(x);
+            if value == 0.0 {
+                return x;
+            }
+            if (value > 0.0) == rising {
+                high = x;
+            } else {
+                low = x;
+            }
+
+            let newton = x - value / derivative.evaluate(x);
+            let next = if newton > low && newton < high {
+                newton
+            } else {
+                0.5 * (low + high)
+            };
+            if next == x || high - low <= f64::EPSILON * x.abs() {
+                return next;
+            }
+            x = next;
+        }
+
+        x
+    }
+
+    /// Distinct real roots in increasing order. Roots closer together than
+    /// the precision with which the polynomial can be evaluated are merged.
+    pub fn into_solutions(mut self) -> Vec<f64> {
+        while self.coefficients.last() == Some(&0.0) {
+            self.coefficients.pop();
         }
 
-        panic!("Solving polynomials of higher degrees is not implemented yet!");
+        match self.coefficients.len() {
+            0 | 1 => Vec::new(),
+            2 => vec!(-self.coefficients[0] / self.coefficients[1]),
+            3 => Self::solve_quadratic(self.coefficients[2], self.coefficients[1], self.coefficients[0]),
+            _ => self.solve_by_extrema(),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Monic polynomial with the given roots.
+    fn from_roots(roots: &[f64]) -> Polynomial {
+        let coefficients = roots.iter().fold(vec![1.0], |coefficients, root| {
+            let mut product = vec![0.0; coefficients.len() + 1];
+            for (power, coefficient) in coefficients.iter().enumerate() {
+                product[power + 1] += coefficient;
+                product[power] -= root * coefficient;
+            }
+            product
+        });
+        Polynomial::new(coefficients)
+    }
+
+    /// Compares the roots with a tolerance relative to their magnitude, or
+    /// absolute below 1.
+    fn assert_roots(polynomial: Polynomial, expected: &[f64], tolerance: f64) {
+        let roots = polynomial.into_solutions();
+
+        assert_eq!(roots.len(), expected.len(), "{:?} != {:?}", roots, expected);
+        for (root, expected) in roots.iter().zip(expected) {
+            let error = (root - expected).abs() / expected.abs().max(1.0);
+            assert!(error <= tolerance, "{:?} != {:?}", roots, expected);
+        }
+    }
 
     #[test]
     pub fn solve_quadratic_one_solution() {
@@ -42,7 +191,7 @@ mod tests {
     pub fn solve_quadratic_no_solutions() {
         let polynomial = Polynomial::new(vec!(1.0, 0.0, 1.0));
 
-        assert_eq!(polynomial.into_solutions(), Vec::new());
+        assert_eq!(polynomial.into_solutions(), Vec::<f64>::new());
     }
 
     #[test]
@@ -51,4 +200,158 @@ mod tests {
 
         assert_eq!(polynomial.into_solutions(), vec!(-1.0, 1.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn solve_quadratic_sorts_solutions() {
+        let polynomial = Polynomial::new(vec!(2.0, -1.0, -1.0));
+
+        assert_eq!(polynomial.into_solutions(), vec!(-2.0, 1.0));
+    }
+
+    #[test]
+    pub fn solve_quadratic_without_cancellation() {
+        // Roots 1e-8 and 1e8: the small one is lost to cancellation by the
+        // textbook formula.
+        let polynomial = Polynomial::new(vec!(1.0, -1e8 - 1e-8, 1.0));
+
+        assert_roots(polynomial, &[1e-8, 1e8], 1e-20);
+    }
+
+    #[test]
+    pub fn solve_low_degrees() {
+        assert_eq!(Polynomial::new(vec!()).into_solutions(), Vec::<f64>::new());
+        assert_eq!(Polynomial::new(vec!(3.0)).into_solutions(), Vec::<f64>::new());
+        assert_eq!(Polynomial::new(vec!(3.0, -2.0)).into_solutions(), vec!(1.5));
+    }
+
+    #[test]
+    pub fn trims_vanishing_leading_coefficients() {
+        let polynomial = Polynomial::new(vec!(-1.0, 0.0, 1.0, 0.0, 0.0));
+        assert_eq!(polynomial.into_solutions(), vec!(-1.0, 1.0));
+        assert_eq!(Polynomial::new(vec!(0.0, 0.0, 0.0)).into_solutions(), Vec::<f64>::new());
+    }
+
+    #[test]
+    pub fn evaluate_and_derivative() {
+        let polynomial = Polynomial::new(vec!(1.0, -3.0, 0.0, 2.0));
+
+        assert_eq!(polynomial.evaluate(2.0), 11.0);
+        assert_eq!(polynomial.derivative().coefficients, vec!(-3.0, 0.0, 6.0));
+    }
+
+    #[test]
+    pub fn solve_cubic_three_solutions() {
+        assert_roots(from_roots(&[3.0, -1.0, 2.0]), &[-1.0, 2.0, 3.0], 1e-12);
+    }
+
+    #[test]
+    pub fn solve_cubic_one_solution() {
+        // x^3 + x + 1 has a single real root.
+        let polynomial = Polynomial::new(vec!(1.0, 1.0, 0.0, 1.0));
+
+        assert_roots(polynomial, &[-0.682_327_803_828_019_3], 1e-15);
+    }
+
+    #[test]
+    pub fn solve_cubic_negative_leading_coefficient() {
+        // -(x - 1)(x - 2)(x - 3)
+        let polynomial = Polynomial::new(vec!(6.0, -11.0, 6.0, -1.0));
+
+        assert_roots(polynomial, &[1.0, 2.0, 3.0], 1e-12);
+    }
+
+    #[test]
+    pub fn solve_cubic_double_root() {
+        assert_roots(from_roots(&[1.0, 1.0, -2.0]), &[-2.0, 1.0], 1e-7);
+        assert_roots(from_roots(&[0.5, -3.0, -3.0]), &[-3.0, 0.5], 1e-7);
+    }
+
+    #[test]
+    pub fn solve_cubic_triple_root() {
+        assert_roots(from_roots(&[1.0, 1.0, 1.0]), &[1.0], 1e-5);
+        assert_roots(Polynomial::new(vec!(0.0, 0.0, 0.0, 2.0)), &[0.0], 0.0);
+    }
+
+    #[test]
+    pub fn solve_cubic_close_roots() {
+        assert_roots(from_roots(&[1.0, 1.0 + 1e-4, -5.0]), &[-5.0, 1.0, 1.0 + 1e-4], 1e-10);
+    }
+
+    #[test]
+    pub fn solve_cubic_zero_root() {
+        assert_roots(from_roots(&[0.0, 2.0, -7.0]), &[-7.0, 0.0, 2.0], 1e-12);
+    }
+
+    #[test]
+    pub fn solve_quartic_four_solutions() {
+        assert_roots(from_roots(&[4.0, 1.0, 3.0, 2.0]), &[1.0, 2.0, 3.0, 4.0], 1e-12);
+    }
+
+    #[test]
+    pub fn solve_quartic_no_solutions() {
+        assert_roots(Polynomial::new(vec!(1.0, 0.0, 0.0, 0.0, 1.0)), &[], 0.0);
+        assert_roots(Polynomial::new(vec!(5.0, 0.0, 3.0, 0.0, 1.0)), &[], 0.0);
+    }
+
+    #[test]
+    pub fn solve_quartic_two_solutions() {
+        // (x^2 + 1)(x - 3)(x + 0.5)
+        let polynomial = Polynomial::new(vec!(-1.5, -2.5, -0.5, -2.5, 1.0));
+
+        assert_roots(polynomial, &[-0.5, 3.0], 1e-12);
+    }
+
+    #[test]
+    pub fn solve_quartic_double_roots() {
+        assert_roots(from_roots(&[1.0, 1.0, 2.0, 2.0]), &[1.0, 2.0], 1e-7);
+        assert_roots(from_roots(&[-1.0, 3.0, 3.0, 5.0]), &[-1.0, 3.0, 5.0], 1e-7);
+        assert_roots(from_roots(&[0.0, 0.0, 1.0, -1.0]), &[-1.0, 0.0, 1.0], 1e-7);
+    }
+
+    #[test]
+    pub fn solve_quartic_triple_and_quadruple_roots() {
+        assert_roots(from_roots(&[2.0, 2.0, 2.0, -1.0]), &[-1.0, 2.0], 1e-4);
+        assert_roots(from_roots(&[2.0, 2.0, 2.0, 2.0]), &[2.0], 1e-3);
+    }
+
+    #[test]
+    pub fn solve_quartic_close_roots() {
+        let polynomial = from_roots(&[1.0, 1.0 + 1e-5, 3.0, -2.0]);
+
+        assert_roots(polynomial, &[-2.0, 1.0, 1.0 + 1e-5, 3.0], 1e-10);
+    }
+
+    #[test]
+    pub fn solve_quartic_roots_of_different_scales() {
+        assert_roots(from_roots(&[1e-3, 1e3, 1.0, -10.0]), &[-10.0, 1e-3, 1.0, 1e3], 1e-9);
+    }
+
+    #[test]
+    pub fn solve_quartic_touching_double_root() {
+        // A ray grazing a torus: (x - 1)^2 (x^2 + 1) touches zero without
+        // crossing it.
+        let polynomial = Polynomial::new(vec!(1.0, -2.0, 2.0, -2.0, 1.0));
+
+        assert_roots(polynomial, &[1.0], 1e-7);
+    }
+
+    #[test]
+    pub fn solve_random_polynomials() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for degree in 3..=4 {
+            for _ in 0..1000 {
+                let mut roots: Vec<f64> = (0..degree).map(|_| rng.gen_range(-10.0, 10.0)).collect();
+                roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                if roots.windows(2).any(|pair| pair[1] - pair[0] < 1e-2) {
+                    continue;
+                }
+                let scale = rng.gen_range(0.1, 10.0);
+                let coefficients = from_roots(&roots).coefficients;
+                let polynomial = Polynomial::new(coefficients.iter().map(|c| c * scale).collect());
+
+                assert_roots(polynomial, &roots, 1e-9);
+            }
+        }
+    }
+}
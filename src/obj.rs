@@ -0,0 +1,272 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead};
+
+use crate::color::Color;
+use crate::shapes::{Material, Mesh, Vertex};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "{}", error),
+            ObjError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> Self {
+        ObjError::Io(error)
+    }
+}
+
+pub fn load(filename: &str, color: Color, material: Material) -> Result<Mesh, ObjError> {
+    let file = fs::File::open(filename)?;
+    parse(io::BufReader::new(file), color, material)
+}
+
+pub fn parse<R: BufRead>(reader: R, color: Color, material: Material) -> Result<Mesh, ObjError> {
+    let mut positions = Vec::new();
+    let mut tex_coords = Vec::new();
+    let mut normals = Vec::new();
+    let mut faces = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let error = |message: String| ObjError::Parse {
+            line: line_number,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats::<3>(&arguments).map_err(error)?;
+                positions.push(Point3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = parse_floats::<2>(&arguments).map_err(error)?;
+                tex_coords.push(Point2::new(u, v));
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats::<3>(&arguments).map_err(error)?;
+                normals.push(Unit::new_normalize(Vector3::new(x, y, z)));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(error(String::from("face needs at least 3 vertices")));
+                }
+                let vertices = arguments
+                    .iter()
+                    .map(|argument| {
+                        parse_vertex(argument, positions.len(), tex_coords.len(), normals.len())
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+
+                // Polygons are split into a fan of triangles around the first vertex.
+                for i in 1..vertices.len() - 1 {
+                    faces.push([vertices[0], vertices[i], vertices[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Mesh::new(positions, tex_coords, normals, faces, color, material))
+}
+
+fn parse_floats<const N: usize>(arguments: &[&str]) -> Result<[f64; N], String> {
+    if arguments.len() < N {
+        return Err(format!("expected {} numbers, found {}", N, arguments.len()));
+    }
+
+    let mut values = [0.0; N];
+    for (value, argument) in values.iter_mut().zip(arguments) {
+        *value = argument
+            .parse()
+            .map_err(|_| format!("invalid number '{}'", argument))?;
+    }
+    Ok(values)
+}
+
+fn parse_index(index: &str, count: usize) -> Result<usize, String> {
+    let index: i64 = index
+        .parse()
+        .map_err(|_| format!("invalid index '{}'", index))?;
+
+    // Indices are 1-based; negative indices count back from the last element.
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} out of range", index));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_vertex(
+    vertex: &str,
+    positions: usize,
+    tex_coords: usize,
+    normals: usize,
+) -> Result<Vertex, String> {
+    let mut parts = vertex.split('/');
+    let position = parse_index(parts.next().unwrap_or(""), positions)?;
+    let tex_coord = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(parse_index(index, tex_coords)?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(index) => Some(parse_index(index, normals)?),
+    };
+
+    Ok(Vertex {
+        position,
+        tex_coord,
+        normal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Line3d;
+    use crate::shapes::Shape;
+
+    #[test]
+    pub fn parse_vertex_formats() {
+        assert_eq!(
+            parse_vertex("2", 3, 3, 3),
+            Ok(Vertex { position: 1, tex_coord: None, normal: None })
+        );
+        assert_eq!(
+            parse_vertex("1/3", 3, 3, 3),
+            Ok(Vertex { position: 0, tex_coord: Some(2), normal: None })
+        );
+        assert_eq!(
+            parse_vertex("1//2", 3, 3, 3),
+            Ok(Vertex { position: 0, tex_coord: None, normal: Some(1) })
+        );
+        assert_eq!(
+            parse_vertex("-1/-2/-3", 3, 3, 3),
+            Ok(Vertex { position: 2, tex_coord: Some(1), normal: Some(0) })
+        );
+    }
+
+    #[test]
+    pub fn parse_vertex_out_of_range() {
+        assert!(parse_vertex("4", 3, 0, 0).is_err());
+        assert!(parse_vertex("0", 3, 0, 0).is_err());
+        assert!(parse_vertex("1/1", 3, 0, 0).is_err());
+    }
+
+    #[test]
+    pub fn parse_triangulates_polygons() {
+        let source = "\
+            v 0 0 0\n\
+            v 1 0 0\n\
+            v 1 1 0\n\
+            v 0 1 0\n\
+            f 1 2 3 4\n";
+
+        let mesh = parse(source.as_bytes(), Color::gray(255), Material::default()).unwrap();
+        let down = -Vector3::z_axis();
+
+        let hits = |x, y| mesh.intersect(&Line3d::new(Point3::new(x, y, 1.0), down)).len();
+        assert_eq!(hits(0.75, 0.25), 1);
+        assert_eq!(hits(0.25, 0.75), 1);
+        assert_eq!(hits(1.5, 0.5), 0);
+    }
+
+    #[test]
+    pub fn parse_interpolates_tex_coords() {
+        let source = "\
+            v 0 0 0\n\
+            v 2 0 0\n\
+            v 0 2 0\n\
+            vt 0 0\n\
+            vt 1 0\n\
+            vt 0 0.5\n\
+            f 1/1 2/2 3/3\n";
+
+        let mesh = parse(source.as_bytes(), Color::gray(255), Material::default()).unwrap();
+
+        assert_eq!(mesh.uv_at(&Point3::new(1.0, 1.0, 0.0)), Point2::new(0.5, 0.25));
+    }
+
+    #[test]
+    pub fn tangent_follows_tex_coords() {
+        // Texture rotated by a quarter turn: u increases along -Y.
+        let source = "\
+            v 0 0 0\n\
+            v 1 0 0\n\
+            v 0 1 0\n\
+            vt 0 0\n\
+            vt 0 1\n\
+            vt -1 0\n\
+            f 1/1 2/2 3/3\n";
+
+        let mesh = parse(source.as_bytes(), Color::gray(255), Material::default()).unwrap();
+
+        assert_eq!(mesh.tangent_at(&Point3::new(0.2, 0.2, 0.0)), Vector3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    pub fn sampling_covers_solid_angle() {
+        let source = "\
+            v -0.5 -0.5 0\n\
+            v 0.5 -0.5 0\n\
+            v 0.5 0.5 0\n\
+            v -0.5 0.5 0\n\
+            f 1 2 3 4\n";
+        let mesh = parse(source.as_bytes(), Color::gray(255), Material::default()).unwrap();
+        let from = Point3::new(0.0, 0.0, 1.0);
+
+        // The mean of 1 / pdf estimates the solid angle of the unit square.
+        let samples = 100;
+        let solid_angle: f64 = iproduct!(0..samples, 0..samples)
+            .map(|(i, j)| {
+                let random = ((i as f64 + 0.5) / samples as f64, (j as f64 + 0.5) / samples as f64);
+                let (position, pdf) = mesh.sample_surface(&from, random).unwrap();
+                assert!(position.z.abs() < 1e-12);
+                assert!(position.x.abs() <= 0.5 && position.y.abs() <= 0.5);
+                1.0 / pdf
+            })
+            .sum::<f64>()
+            / (samples * samples) as f64;
+
+        let expected = 4.0 * (1.0 / 5.0f64).asin();
+        assert!((solid_angle - expected).abs() < 1e-3, "{} {}", solid_angle, expected);
+    }
+
+    #[test]
+    pub fn parse_reports_line_of_error() {
+        let source = "v 0 0 0\nv 1 0\n";
+
+        match parse(source.as_bytes(), Color::gray(255), Material::default()) {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("Expected a parse error"),
+        }
+    }
+}
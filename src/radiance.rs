@@ -0,0 +1,138 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
+
+use crate::color::Color;
+use crate::tone_mapping::decode_srgb;
+
+/// Linear, unbounded RGB light intensity. Shading accumulates radiance and it
+/// is only tone mapped into a `Color` once the image is written out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Radiance {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+impl fmt::Display for Radiance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Radiance({}, {}, {})", self.r, self.g, self.b)
+    }
+}
+
+impl Radiance {
+    pub fn new(r: f64, g: f64, b: f64) -> Self {
+        Radiance { r, g, b }
+    }
+
+    pub fn black() -> Self {
+        Radiance::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn gray(intensity: f64) -> Self {
+        Radiance::new(intensity, intensity, intensity)
+    }
+
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Linear interpolation, returning `other` for `ratio` equal to 1.
+    pub fn mix(&self, other: &Radiance, ratio: f64) -> Radiance {
+        let ratio = ratio.clamp(0.0, 1.0);
+        *self * (1.0 - ratio) + *other * ratio
+    }
+
+    /// Adds `other` weighted by `ratio` in quadrature, so that a dim
+    /// reflection does not wash out the surface it is seen in.
+    pub fn blend(&self, other: &Radiance, ratio: f64) -> Radiance {
+        let ratio = ratio.clamp(0.0, 1.0);
+        let component = |a: f64, b: f64| (a.powi(2) + b.powi(2) * ratio).sqrt();
+        Radiance::new(
+            component(self.r, other.r),
+            component(self.g, other.g),
+            component(self.b, other.b),
+        )
+    }
+}
+
+/// Colors are authored in sRGB, so they are decoded into linear values.
+impl From<&Color> for Radiance {
+    fn from(color: &Color) -> Self {
+        let component = |value: u8| decode_srgb(f64::from(value) / 255.0);
+        Radiance::new(component(color.r), component(color.g), component(color.b))
+    }
+}
+
+impl From<Color> for Radiance {
+    fn from(color: Color) -> Self {
+        Radiance::from(&color)
+    }
+}
+
+impl Add for Radiance {
+    type Output = Radiance;
+
+    fn add(self, other: Radiance) -> Radiance {
+        Radiance::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+}
+
+impl AddAssign for Radiance {
+    fn add_assign(&mut self, other: Radiance) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Radiance {
+    type Output = Radiance;
+
+    fn sub(self, other: Radiance) -> Radiance {
+        Radiance::new(self.r - other.r, self.g - other.g, self.b - other.b)
+    }
+}
+
+impl Mul for Radiance {
+    type Output = Radiance;
+
+    fn mul(self, other: Radiance) -> Radiance {
+        Radiance::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+}
+
+impl Mul<f64> for Radiance {
+    type Output = Radiance;
+
+    fn mul(self, factor: f64) -> Radiance {
+        Radiance::new(self.r * factor, self.g * factor, self.b * factor)
+    }
+}
+
+impl Div<f64> for Radiance {
+    type Output = Radiance;
+
+    fn div(self, divisor: f64) -> Radiance {
+        Radiance::new(self.r / divisor, self.g / divisor, self.b / divisor)
+    }
+}
+
+impl Sum for Radiance {
+    fn sum<I: Iterator<Item = Radiance>>(iter: I) -> Radiance {
+        iter.fold(Radiance::black(), |sum, radiance| sum + radiance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn accumulation_does_not_clip() {
+        let bright = Radiance::gray(0.8);
+
+        let sum = bright + bright + bright;
+
+        assert_eq!(sum, Radiance::gray(0.8 + 0.8 + 0.8));
+        assert_eq!(sum / 3.0, Radiance::gray((0.8 + 0.8 + 0.8) / 3.0));
+    }
+}
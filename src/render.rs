@@ -1,23 +1,63 @@
+mod path_tracing;
+
 use std::cmp;
+use std::f64::consts::PI;
+use std::str::FromStr;
 
+use crate::bvh::Bvh;
 use crate::camera::Camera;
 use crate::canvas::Canvas;
-use crate::color::Color;
-use crate::geometry::{utils::*, Line3d};
-use crate::light::Light;
+use crate::geometry::{utils::*, BoundingBox, Line3d};
 use crate::progress_bar::ProgressBar;
+use crate::radiance::Radiance;
+use crate::sampling;
 use crate::scene::Scene;
 use crate::shapes::Shape;
 
-use nalgebra::Point3;
+use nalgebra::{Point3, Unit, Vector3};
+use rand::Rng;
 use rayon::prelude::*;
 
+/// Exponent of the Phong specular highlight.
+const SPECULAR_EXPONENT: i32 = 5;
+/// Number of bounces followed by the Whitted integrator.
+const MAX_DEPTH: usize = 8;
+/// Rays averaged into the reflection of a rough surface seen directly. Each
+/// further bounce casts a quarter as many.
+const GLOSSY_SAMPLES: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+    /// Whitted-style ray tracing: direct lighting plus mirror reflections
+    /// and refraction.
+    Whitted,
+    /// Unidirectional Monte Carlo path tracing with global illumination.
+    PathTracing,
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "whitted" => Ok(Integrator::Whitted),
+            "path" => Ok(Integrator::PathTracing),
+            _ => Err(format!("Unknown integrator '{}'", name)),
+        }
+    }
+}
+
 pub struct Render<'a> {
     scene: &'a Scene,
     camera: &'a Camera,
     width: usize,
     height: usize,
     pixel_radius: f64,
+    bvh: Bvh,
+    /// Indices of the shapes with emissive materials.
+    emitters: Vec<usize>,
+    integrator: Integrator,
+    samples: usize,
 }
 
 impl<'a> Render<'a> {
@@ -28,86 +68,305 @@ impl<'a> Render<'a> {
             width,
             height,
             pixel_radius: 1.0 / (cmp::max(width, height) as f64),
+            bvh: Bvh::new(&Self::shape_bounds(scene)),
+            emitters: scene
+                .shapes()
+                .iter()
+                .enumerate()
+                .filter(|(_, shape)| shape.material().emission.luminance() > 0.0)
+                .map(|(index, _)| index)
+                .collect(),
+            integrator: Integrator::Whitted,
+            samples: 1,
         }
     }
 
-    fn cast(&self, ray: &Line3d, depth: usize) -> Color {
+    /// Number of rays averaged for every pixel.
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// Tests every ray against every shape instead of using the BVH.
+    pub fn without_bvh(mut self) -> Self {
+        self.bvh = Bvh::flat(&Self::shape_bounds(self.scene));
+        self
+    }
+
+    fn shape_bounds(scene: &Scene) -> Vec<Option<BoundingBox>> {
+        scene
+            .shapes()
+            .iter()
+            .map(|shape| shape.bounding_box())
+            .collect()
+    }
+
+    /// Traces a ray through the scene. `media` holds the refractive indices of
+    /// the transparent shapes the ray is currently inside of, innermost last.
+    fn cast(&self, ray: &Line3d, depth: usize, media: &[f64]) -> Radiance {
         if depth == 0 {
-            return self.scene.background();
+            return self.scene.background_radiance(&ray.direction());
         }
 
         let nearest_intersection = self.nearest_intersection(ray);
         if nearest_intersection.is_none() {
-            return self.scene.background();
+            return self.scene.background_radiance(&ray.direction());
         }
 
         let (shape, point) = nearest_intersection.unwrap();
-        let color = self.color_at(shape, &point, self.camera.center());
+        let emission = shape.material().emission;
+        let mut color = emission + self.color_at(shape, &point, self.camera.center());
 
-        if depth == 1 || shape.material().reflectiveness <= 0.0 {
+        if depth == 1 {
             return color;
         }
 
-        let reflected_ray = shape.reflect(ray, &point);
-        let deep_color = self.cast(&reflected_ray, depth - 1);
-        color.blend(&deep_color, 0.5)
+        if shape.material().transparency > 0.0 {
+            let through = self.cast_through(shape, ray, &point, depth, media);
+            return color.mix(&through, shape.material().transparency);
+        }
+
+        if let Some(pbr) = shape.material().pbr.filter(|pbr| pbr.roughness < 1.0) {
+            // Reflected rays stand in for the specular lobe, fading out as
+            // the lobe widens.
+            let reflected = self.cast_reflection(shape, ray, &point, pbr.roughness, depth, media);
+            let cos_view = -ray.direction().dot(&shape.shading_normal_at(&point));
+            let reflectance = pbr.fresnel(&shape.color_at(&point), cos_view);
+            let glossiness = (1.0 - pbr.roughness).powi(2);
+            color += reflected * reflectance * glossiness;
+        }
+
+        let material = shape.material();
+        if material.reflectiveness <= 0.0 {
+            return color;
+        }
+
+        let roughness = material.reflection_roughness();
+        let deep_color = self.cast_reflection(shape, ray, &point, roughness, depth, media);
+        color.blend(&deep_color, material.reflectiveness)
     }
 
-    fn nearest_intersection(&self, ray: &Line3d) -> Option<(&dyn Shape, Point3<f64>)> {
-        self.scene
-            .shapes()
-            .iter()
-            .flat_map(|shape| shape.intersect(ray))
-            .filter(|&(_, position)| ray.project(position) > 0.0)
-            .filter(|&(_, position)| position.distance_to(&ray.origin()) > self.pixel_radius)
-            .min_by(|&(_, a), &(_, b)| ray.project(a).partial_cmp(&ray.project(b)).unwrap())
+    /// Color reflected off a surface, averaged over rays spread around the
+    /// mirror direction according to the roughness.
+    fn cast_reflection(
+        &self,
+        shape: &dyn Shape,
+        ray: &Line3d,
+        point: &Point3<f64>,
+        roughness: f64,
+        depth: usize,
+        media: &[f64],
+    ) -> Radiance {
+        if roughness <= 0.0 {
+            return self.cast(&shape.reflect(ray, point), depth - 1, media);
+        }
+
+        let samples = (GLOSSY_SAMPLES >> (2 * (MAX_DEPTH - depth))).max(1);
+        let mut rng = rand::thread_rng();
+        let sum: Radiance = (0..samples)
+            .map(|_| {
+                let reflected_ray = Self::glossy_reflection(shape, ray, point, roughness, &mut rng);
+                self.cast(&reflected_ray, depth - 1, media)
+            })
+            .sum();
+        sum / samples as f64
     }
 
-    fn color_at(&self, shape: &dyn Shape, point: &Point3<f64>, eye: &Point3<f64>) -> Color {
-        shape
-            .color_at(point)
-            .dim(self.illumination_at(shape, point, eye))
+    /// Mirrors the ray about a microfacet normal drawn from the GGX
+    /// distribution with the given roughness. Falls back to the mirror
+    /// direction if the perturbed ray would go into the surface.
+    fn glossy_reflection<R: Rng>(
+        shape: &dyn Shape,
+        ray: &Line3d,
+        point: &Point3<f64>,
+        roughness: f64,
+        rng: &mut R,
+    ) -> Line3d {
+        let normal = shape.shading_normal_at(point);
+        let facing = if ray.direction().dot(&normal) > 0.0 { -normal } else { normal };
+        let microfacet = sampling::ggx(&facing, roughness * roughness, rng);
+        let direction = ray.direction().reflect(&microfacet);
+
+        let geometric = shape.normal_at(point);
+        if direction.dot(&geometric) * ray.direction().dot(&geometric) < 0.0 {
+            Line3d::new(*point, Unit::new_normalize(direction))
+        } else {
+            shape.reflect(ray, point)
+        }
     }
 
-    fn illumination_at(&self, shape: &dyn Shape, point: &Point3<f64>, eye: &Point3<f64>) -> f64 {
-        self.scene
+    /// Color seen through a transparent surface: the refracted and reflected
+    /// rays weighted by Fresnel reflectance.
+    fn cast_through(
+        &self,
+        shape: &dyn Shape,
+        ray: &Line3d,
+        point: &Point3<f64>,
+        depth: usize,
+        media: &[f64],
+    ) -> Radiance {
+        let (n1, n2, inner_media) = Self::cross_surface(shape, ray, point, media);
+
+        let reflected_ray = shape.reflect(ray, point);
+        let reflected = self.cast(&reflected_ray, depth - 1, media);
+
+        match shape.refract(ray, point, n1 / n2) {
+            Some(refracted_ray) => {
+                let refracted = self.cast(&refracted_ray, depth - 1, &inner_media);
+                let cos_incident = ray.direction().dot(&shape.normal_at(point)).abs();
+                refracted.mix(&reflected, Self::fresnel(cos_incident, n1, n2))
+            }
+            // Total internal reflection
+            None => reflected,
+        }
+    }
+
+    /// Refractive indices on both sides of a transparent surface hit by the
+    /// ray, and the stack of media the ray will be in once it passes through.
+    fn cross_surface(
+        shape: &dyn Shape,
+        ray: &Line3d,
+        point: &Point3<f64>,
+        media: &[f64],
+    ) -> (f64, f64, Vec<f64>) {
+        let outside_index = |media: &[f64]| media.last().cloned().unwrap_or(1.0);
+        let shape_index = shape.material().refractive_index;
+
+        let mut inner_media = media.to_vec();
+        if shape.entering(ray, point) {
+            inner_media.push(shape_index);
+            (outside_index(media), shape_index, inner_media)
+        } else {
+            if let Some(position) = inner_media.iter().rposition(|&index| index == shape_index) {
+                inner_media.remove(position);
+            }
+            let n2 = outside_index(&inner_media);
+            (shape_index, n2, inner_media)
+        }
+    }
+
+    /// Fraction of light reflected off a surface between media with the given
+    /// refractive indices, using Schlick's approximation.
+    fn fresnel(cos_incident: f64, n1: f64, n2: f64) -> f64 {
+        let cos = if n1 > n2 {
+            // Schlick's approximation needs the cosine on the optically thinner side.
+            let sin2_transmitted = (n1 / n2).powi(2) * (1.0 - cos_incident.powi(2));
+            if sin2_transmitted > 1.0 {
+                return 1.0;
+            }
+            (1.0 - sin2_transmitted).sqrt()
+        } else {
+            cos_incident
+        };
+
+        let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+    }
+
+    fn nearest_intersection(&self, ray: &Line3d) -> Option<(&dyn Shape, Point3<f64>)> {
+        let shapes = self.scene.shapes();
+        self.bvh
+            .nearest(ray, |index| {
+                shapes[index]
+                    .intersect(ray)
+                    .into_iter()
+                    .map(|(shape, position)| (ray.project(position), (shape, position)))
+                    // Also drops the NaN distances of degenerate shapes.
+                    .filter(|&(distance, _)| distance > 0.0)
+                    .filter(|&(_, (_, position))| {
+                        position.distance_to(&ray.origin()) > self.pixel_radius
+                    })
+                    .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(cmp::Ordering::Equal))
+            })
+            .map(|(_, intersection)| intersection)
+    }
+
+    /// Light reflected towards the eye, with the contributions of separate
+    /// lights added in quadrature.
+    fn color_at(&self, shape: &dyn Shape, point: &Point3<f64>, eye: &Point3<f64>) -> Radiance {
+        let base = shape.color_at(point);
+        let mut rng = rand::thread_rng();
+        let mut light_samples: Vec<_> = self
+            .scene
             .lights()
             .iter()
-            .filter(|light| self.path_clear(point, &light.center))
-            .map(|light| Self::illumination_from_light(shape, point, light, eye))
-            .map(|illumination| illumination.powi(2))
-            .sum::<f64>()
-            .sqrt()
-            + shape.material().ambient_light
+            .map(|light| light.sample(point, &mut rng))
+            .collect();
+        if let Some(environment) = self.scene.environment() {
+            light_samples.push(environment.light_samples(environment.samples(), &mut rng));
+        }
+
+        let squares: Radiance = light_samples
+            .into_iter()
+            .map(|samples| {
+                samples
+                    .into_iter()
+                    .filter(|sample| self.path_clear(point, &sample.direction, sample.distance))
+                    .map(|sample| {
+                        sample.radiance
+                            * Self::reflected_from_light(shape, point, &base, &sample.direction, eye)
+                    })
+                    .sum::<Radiance>()
+            })
+            .map(|illumination| illumination * illumination)
+            .sum();
+
+        let ambient = shape.material().ambient_light.max(0.0);
+        Radiance::new(squares.r.sqrt(), squares.g.sqrt(), squares.b.sqrt()) + base * ambient
     }
 
-    fn path_clear(&self, point_on_shape: &Point3<f64>, other_point: &Point3<f64>) -> bool {
-        self.scene
-            .shapes()
-            .iter()
-            .filter(|obstacle| obstacle.occludes(point_on_shape, other_point, self.pixel_radius))
-            .peekable()
-            .peek()
-            .is_none()
+    /// Whether nothing blocks the way from the point along `direction` up to
+    /// `distance`, which is infinite for lights without a position.
+    fn path_clear(&self, point: &Point3<f64>, direction: &Unit<Vector3<f64>>, distance: f64) -> bool {
+        let shapes = self.scene.shapes();
+        let ray = Line3d::new(*point, *direction);
+
+        !self.bvh.any(&ray, 0.0, distance, |index| {
+            shapes[index].occludes(&ray, distance, self.pixel_radius)
+        })
+    }
+
+    /// Fraction of the light arriving along `unit_to_light` that is
+    /// reflected towards the eye by a surface of the given base color.
+    fn reflected_from_light(
+        shape: &dyn Shape,
+        position: &Point3<f64>,
+        base: &Radiance,
+        unit_to_light: &Unit<Vector3<f64>>,
+        eye: &Point3<f64>,
+    ) -> Radiance {
+        match shape.material().pbr {
+            // Scaled by pi so that a white Lambertian surface matches a
+            // diffuse coefficient of 1.
+            Some(pbr) => {
+                let normal = shape.shading_normal_at(position);
+                let unit_to_eye = position.unit_to(eye);
+                let cos_theta = normal.dot(unit_to_light).max(0.0);
+                pbr.evaluate(base, &normal, unit_to_light, &unit_to_eye) * (PI * cos_theta)
+            }
+            None => *base * Self::illumination_from_light(shape, position, unit_to_light, eye),
+        }
     }
 
     fn illumination_from_light(
         shape: &dyn Shape,
         position: &Point3<f64>,
-        light: &Light,
+        unit_to_light: &Unit<Vector3<f64>>,
         eye: &Point3<f64>,
     ) -> f64 {
-        let normal = shape.normal_at(position);
+        let normal = shape.shading_normal_at(position);
 
-        let unit_to_light = position.unit_to(&light.center);
-        let diffuse =
-            normal.dot(&unit_to_light) * shape.material().diffuse_coefficient * light.intensity;
+        let diffuse = normal.dot(unit_to_light).max(0.0) * shape.material().diffuse_coefficient;
 
         let unit_to_eye = position.unit_to(eye);
         let reflection = unit_to_light.reflect(&normal);
-        let specular = -reflection.dot(&unit_to_eye).min(0.0).powi(5)
-            * shape.material().specular_coefficient
-            * light.intensity;
+        let specular = -reflection.dot(&unit_to_eye).min(0.0).powi(SPECULAR_EXPONENT)
+            * shape.material().specular_coefficient;
 
         diffuse + specular
     }
@@ -116,15 +375,24 @@ impl<'a> Render<'a> {
         let progress_bar = ProgressBar::new("Rendering", self.width * self.height);
         let color_points: Vec<_> = self
             .camera
-            .rays(self.width, self.height)
+            .rays(self.width, self.height, self.samples)
             .inspect(|_| progress_bar.step().print())
-            .map(|(x, y, ray)| (x, y, self.cast(&ray, 8)))
+            .map(|(x, y, rays)| {
+                let sum: Radiance = rays
+                    .iter()
+                    .map(|ray| match self.integrator {
+                        Integrator::Whitted => self.cast(ray, MAX_DEPTH, &[]),
+                        Integrator::PathTracing => self.trace_path(ray),
+                    })
+                    .sum();
+                (x, y, sum / rays.len() as f64)
+            })
             .collect();
 
-        let mut canvas = Canvas::new(self.width, self.height, self.scene.background());
-        for (x, y, color) in &color_points {
+        let mut canvas = Canvas::new(self.width, self.height, Radiance::black());
+        for (x, y, radiance) in &color_points {
             if let Some(pixel) = canvas.get_mut(*x, *y) {
-                *pixel = color.clone();
+                *pixel = *radiance;
             }
         }
 
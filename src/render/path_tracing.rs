@@ -0,0 +1,299 @@
+use std::f64::consts::PI;
+
+use crate::geometry::{utils::*, Line3d};
+use crate::radiance::Radiance;
+use crate::render::{Render, SPECULAR_EXPONENT};
+use crate::sampling;
+use crate::shapes::{MetallicRoughness, Shape};
+
+use nalgebra::{Point3, Unit, Vector3};
+use rand::Rng;
+
+const MAX_BOUNCES: usize = 32;
+/// Paths are never terminated by Russian roulette before this many bounces.
+const MIN_BOUNCES: usize = 3;
+
+/// Surface properties at a path vertex, split into the smooth lobes that can
+/// be evaluated for light sampling and the perfectly specular ones.
+struct Surface {
+    albedo: Radiance,
+    normal: Unit<Vector3<f64>>,
+    /// Probabilities of the transmissive and mirror lobes. The remaining
+    /// probability goes to the diffuse and glossy lobes.
+    transmission: f64,
+    mirror: f64,
+    diffuse: f64,
+    glossy: f64,
+    /// Microfacet model replacing the diffuse and glossy lobes.
+    pbr: Option<MetallicRoughness>,
+}
+
+impl Surface {
+    fn new(shape: &dyn Shape, point: &Point3<f64>, outgoing: &Vector3<f64>) -> Self {
+        let material = shape.material();
+        let normal = shape.shading_normal_at(point);
+        // Shade the side of the surface the path arrived from.
+        let normal = if normal.dot(outgoing) < 0.0 { -normal } else { normal };
+
+        let transmission = material.transparency.clamp(0.0, 1.0);
+        Surface {
+            albedo: shape.color_at(point),
+            normal,
+            transmission,
+            mirror: (1.0 - transmission) * material.reflectiveness.clamp(0.0, 1.0),
+            diffuse: material.diffuse_coefficient.max(0.0),
+            glossy: material.specular_coefficient.max(0.0),
+            pbr: material.pbr,
+        }
+    }
+
+    fn smooth(&self) -> f64 {
+        1.0 - self.transmission - self.mirror
+    }
+
+    /// BRDF of the diffuse and glossy lobes.
+    fn evaluate(&self, incoming: &Vector3<f64>, outgoing: &Vector3<f64>) -> Radiance {
+        if let Some(pbr) = &self.pbr {
+            return pbr.evaluate(&self.albedo, &self.normal, incoming, outgoing) * self.smooth();
+        }
+        if incoming.dot(&self.normal) <= 0.0 || outgoing.dot(&self.normal) <= 0.0 {
+            return Radiance::black();
+        }
+
+        let exponent = f64::from(SPECULAR_EXPONENT);
+        let mirrored = (-outgoing).reflect(&self.normal);
+        let cos_alpha = mirrored.dot(incoming).max(0.0);
+        let diffuse = self.albedo * (self.diffuse / PI);
+        let glossy = self.glossy * (exponent + 2.0) / (2.0 * PI) * cos_alpha.powf(exponent);
+
+        (diffuse + Radiance::gray(glossy)) * self.smooth()
+    }
+
+    /// Solid angle density with which the smooth lobes are sampled, including
+    /// the probability of choosing them.
+    fn pdf(&self, incoming: &Vector3<f64>, outgoing: &Vector3<f64>) -> f64 {
+        if let Some(pbr) = &self.pbr {
+            return pbr.pdf(&self.albedo, &self.normal, incoming, outgoing) * self.smooth();
+        }
+        let total = self.diffuse + self.glossy;
+        let cos_theta = incoming.dot(&self.normal);
+        if total <= 0.0 || cos_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let exponent = f64::from(SPECULAR_EXPONENT);
+        let mirrored = (-outgoing).reflect(&self.normal);
+        let cos_alpha = mirrored.dot(incoming).max(0.0);
+        let diffuse = cos_theta / PI;
+        let glossy = (exponent + 1.0) / (2.0 * PI) * cos_alpha.powf(exponent);
+
+        (self.diffuse * diffuse + self.glossy * glossy) / total * self.smooth()
+    }
+}
+
+/// Weight of a sample drawn with density `pdf` when another strategy could
+/// have produced it with density `other`, using the power heuristic.
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (pdf, other) = (pdf * pdf, other * other);
+    if pdf + other > 0.0 {
+        pdf / (pdf + other)
+    } else {
+        0.0
+    }
+}
+
+impl<'a> Render<'a> {
+    /// Estimates the radiance arriving along the ray with a single random path.
+    pub(super) fn trace_path(&self, ray: &Line3d) -> Radiance {
+        let mut rng = rand::thread_rng();
+        let mut radiance = Radiance::black();
+        let mut throughput = Radiance::gray(1.0);
+        let mut ray = *ray;
+        let mut media: Vec<f64> = Vec::new();
+        // Density with which the smooth lobes picked the current ray, or
+        // `None` for camera rays and perfectly specular bounces, which light
+        // sampling cannot produce.
+        let mut smooth_pdf: Option<f64> = None;
+
+        for bounce in 0..MAX_BOUNCES {
+            let (shape, point) = match self.nearest_intersection(&ray) {
+                Some(intersection) => intersection,
+                None => {
+                    let background = self.scene.background_radiance(&ray.direction());
+                    let weight = match (self.scene.environment(), smooth_pdf) {
+                        (Some(environment), Some(pdf)) => {
+                            power_heuristic(pdf, environment.pdf(&ray.direction()))
+                        }
+                        _ => 1.0,
+                    };
+                    radiance += throughput * background * weight;
+                    break;
+                }
+            };
+
+            let emission = shape.material().emission;
+            if emission.luminance() > 0.0 {
+                // Weighted against explicit sampling of the emitter from the
+                // previous vertex.
+                let weight = match smooth_pdf {
+                    Some(pdf) => power_heuristic(pdf, shape.surface_pdf(&ray.origin(), &point)),
+                    None => 1.0,
+                };
+                radiance += throughput * emission * weight;
+            }
+
+            let outgoing = -ray.direction();
+            let surface = Surface::new(shape, &point, &outgoing);
+            if surface.smooth() > 0.0 {
+                radiance += throughput * self.direct_lighting(&surface, &point, &outgoing);
+            }
+
+            smooth_pdf = None;
+            let lobe = rng.gen::<f64>();
+            let direction = if lobe < surface.transmission {
+                let (n1, n2, inner_media) = Self::cross_surface(shape, &ray, &point, &media);
+                let cos_incident = ray.direction().dot(&surface.normal).abs();
+                let refracted = shape.refract(&ray, &point, n1 / n2);
+                match refracted {
+                    Some(refracted) if rng.gen::<f64>() >= Self::fresnel(cos_incident, n1, n2) => {
+                        media = inner_media;
+                        refracted.direction()
+                    }
+                    _ => ray.direction().reflect(&surface.normal),
+                }
+            } else if lobe < surface.transmission + surface.mirror {
+                let roughness = shape.material().reflection_roughness();
+                Self::glossy_reflection(shape, &ray, &point, roughness, &mut rng).direction()
+            } else {
+                match self.sample_smooth(&surface, &outgoing, &mut rng) {
+                    Some((direction, weight)) => {
+                        throughput = throughput * weight;
+                        smooth_pdf = Some(surface.pdf(&direction, &outgoing));
+                        direction
+                    }
+                    None => break,
+                }
+            };
+
+            if bounce >= MIN_BOUNCES {
+                let survival = throughput.r.max(throughput.g).max(throughput.b).min(0.95);
+                if rng.gen::<f64>() >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+
+            ray = Line3d::new(point, Unit::new_normalize(direction));
+        }
+
+        radiance
+    }
+
+    /// Light arriving directly from the scene's lights and environment,
+    /// reflected by the smooth lobes towards `outgoing`.
+    fn direct_lighting(&self, surface: &Surface, point: &Point3<f64>, outgoing: &Vector3<f64>) -> Radiance {
+        let lights: Radiance = self
+            .scene
+            .lights()
+            .iter()
+            .flat_map(|light| light.sample(point, &mut rand::thread_rng()))
+            .filter(|sample| sample.radiance.luminance() > 0.0)
+            .filter(|sample| self.path_clear(point, &sample.direction, sample.distance))
+            .map(|sample| {
+                let cos_theta = sample.direction.dot(&surface.normal).max(0.0);
+                surface.evaluate(&sample.direction, outgoing) * sample.radiance * cos_theta
+            })
+            .sum();
+
+        lights
+            + self.emitter_lighting(surface, point, outgoing)
+            + self.environment_lighting(surface, point, outgoing)
+    }
+
+    /// Samples a single point on every emissive shape, weighted against paths
+    /// that hit the emitters through the smooth lobes.
+    fn emitter_lighting(&self, surface: &Surface, point: &Point3<f64>, outgoing: &Vector3<f64>) -> Radiance {
+        let mut rng = rand::thread_rng();
+        let shapes = self.scene.shapes();
+
+        self.emitters
+            .iter()
+            .filter_map(|&index| {
+                let emitter = &shapes[index];
+                let (position, pdf) = emitter.sample_surface(point, (rng.gen(), rng.gen()))?;
+                let to_emitter = position - point;
+                let distance = to_emitter.norm();
+                let direction = Unit::new_normalize(to_emitter);
+                let cos_theta = direction.dot(&surface.normal);
+                if pdf <= 0.0 || cos_theta <= 0.0 || !self.path_clear(point, &direction, distance) {
+                    return None;
+                }
+
+                let weight = power_heuristic(pdf, surface.pdf(&direction, outgoing));
+                let emission = emitter.material().emission;
+                Some(surface.evaluate(&direction, outgoing) * emission * (cos_theta * weight / pdf))
+            })
+            .sum()
+    }
+
+    /// Samples a single direction of the environment map, weighted against
+    /// paths that reach the environment through the smooth lobes.
+    fn environment_lighting(&self, surface: &Surface, point: &Point3<f64>, outgoing: &Vector3<f64>) -> Radiance {
+        let environment = match self.scene.environment() {
+            Some(environment) => environment,
+            None => return Radiance::black(),
+        };
+
+        let (direction, arriving, pdf) = environment.sample(&mut rand::thread_rng());
+        let cos_theta = direction.dot(&surface.normal);
+        if pdf <= 0.0 || cos_theta <= 0.0 || !self.path_clear(point, &direction, f64::INFINITY) {
+            return Radiance::black();
+        }
+
+        let weight = power_heuristic(pdf, surface.pdf(&direction, outgoing));
+        surface.evaluate(&direction, outgoing) * arriving * (cos_theta * weight / pdf)
+    }
+
+    /// Importance samples the diffuse or glossy lobe, returning the new
+    /// direction and the path throughput weight (BRDF * cos / pdf).
+    fn sample_smooth<R: Rng>(
+        &self,
+        surface: &Surface,
+        outgoing: &Vector3<f64>,
+        rng: &mut R,
+    ) -> Option<(Vector3<f64>, Radiance)> {
+        if let Some(pbr) = &surface.pbr {
+            let direction = pbr.sample(&surface.albedo, &surface.normal, outgoing, rng)?;
+            let pdf = pbr.pdf(&surface.albedo, &surface.normal, &direction, outgoing);
+            if pdf <= 0.0 {
+                return None;
+            }
+            let brdf = pbr.evaluate(&surface.albedo, &surface.normal, &direction, outgoing);
+            return Some((direction, brdf * (direction.dot(&surface.normal) / pdf)));
+        }
+
+        let total = surface.diffuse + surface.glossy;
+        if total <= 0.0 {
+            return None;
+        }
+
+        let diffuse_probability = surface.diffuse / total;
+        if rng.gen::<f64>() < diffuse_probability {
+            let direction = sampling::cosine_hemisphere(&surface.normal, rng);
+            let weight = surface.albedo * (surface.diffuse / diffuse_probability);
+            Some((direction.into_inner(), weight))
+        } else {
+            let exponent = f64::from(SPECULAR_EXPONENT);
+            let mirrored = Unit::new_normalize((-outgoing).reflect(&surface.normal));
+            let direction = sampling::phong_lobe(&mirrored, exponent, rng);
+            let cos_theta = direction.dot(&surface.normal);
+            if cos_theta <= 0.0 {
+                return None;
+            }
+
+            let weight = surface.glossy * (exponent + 2.0) / (exponent + 1.0) * cos_theta
+                / (1.0 - diffuse_probability);
+            Some((direction.into_inner(), Radiance::gray(weight)))
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::f64::consts::PI;
+
+use nalgebra::{Point3, Unit, Vector3};
+use rand::Rng;
+
+/// Two unit vectors perpendicular to `normal` and to each other.
+pub fn orthonormal_basis(normal: &Unit<Vector3<f64>>) -> (Vector3<f64>, Vector3<f64>) {
+    let helper = if normal.x.abs() > 0.9 {
+        Vector3::y()
+    } else {
+        Vector3::x()
+    };
+    let tangent = normal.cross(&helper).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+/// Solid angle density, as seen from `from`, of picking `position` uniformly
+/// over a flat surface of the given area and normal.
+pub fn area_pdf(
+    from: &Point3<f64>,
+    position: &Point3<f64>,
+    normal: &Unit<Vector3<f64>>,
+    area: f64,
+) -> f64 {
+    let to_surface = position - from;
+    let distance2 = to_surface.norm_squared();
+    let cos_light = normal.dot(&to_surface).abs() / distance2.sqrt();
+    if cos_light <= 0.0 || area <= 0.0 {
+        return 0.0;
+    }
+    distance2 / (cos_light * area)
+}
+
+/// Direction given in spherical coordinates around `axis`.
+pub fn around(axis: &Unit<Vector3<f64>>, cos_theta: f64, phi: f64) -> Unit<Vector3<f64>> {
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    Unit::new_normalize(
+        tangent * (sin_theta * phi.cos())
+            + bitangent * (sin_theta * phi.sin())
+            + axis.into_inner() * cos_theta,
+    )
+}
+
+/// Samples the hemisphere around `normal` with density `cos(theta) / pi`.
+pub fn cosine_hemisphere<R: Rng>(normal: &Unit<Vector3<f64>>, rng: &mut R) -> Unit<Vector3<f64>> {
+    let cos_theta = rng.gen::<f64>().sqrt();
+    around(normal, cos_theta, 2.0 * PI * rng.gen::<f64>())
+}
+
+/// Samples directions around `axis` with density proportional to
+/// `cos(alpha)^exponent`, i.e. `(exponent + 1) / (2 pi) * cos(alpha)^exponent`.
+pub fn phong_lobe<R: Rng>(
+    axis: &Unit<Vector3<f64>>,
+    exponent: f64,
+    rng: &mut R,
+) -> Unit<Vector3<f64>> {
+    let cos_alpha = rng.gen::<f64>().powf(1.0 / (exponent + 1.0));
+    around(axis, cos_alpha, 2.0 * PI * rng.gen::<f64>())
+}
+
+/// Samples microfacet normals around `normal` from the GGX distribution with
+/// roughness `alpha`, with density `D(h) * cos(theta_h)`.
+pub fn ggx<R: Rng>(normal: &Unit<Vector3<f64>>, alpha: f64, rng: &mut R) -> Unit<Vector3<f64>> {
+    let u = rng.gen::<f64>();
+    let cos_theta = ((1.0 - u) / (1.0 + (alpha * alpha - 1.0) * u)).sqrt();
+    around(normal, cos_theta, 2.0 * PI * rng.gen::<f64>())
+}
+
+/// Positions within the unit square. The largest square grid of strata that
+/// fits in `count` gets one jittered sample each; any remaining samples are
+/// placed uniformly at random.
+pub fn stratified_square<R: Rng>(count: usize, rng: &mut R) -> Vec<(f64, f64)> {
+    let strata = (count as f64).sqrt().floor() as usize;
+    let stratum_size = 1.0 / strata as f64;
+
+    let stratified = iproduct!(0..strata, 0..strata).map(|(i, j)| {
+        (
+            (i as f64 + rng.gen::<f64>()) * stratum_size,
+            (j as f64 + rng.gen::<f64>()) * stratum_size,
+        )
+    });
+    let mut samples: Vec<(f64, f64)> = stratified.collect();
+    samples.extend((samples.len()..count).map(|_| (rng.gen::<f64>(), rng.gen::<f64>())));
+
+    samples
+}
+
+/// Distribution over `[0, 1)` split into equally wide bins with densities
+/// proportional to the given weights.
+pub struct PiecewiseConstant {
+    /// Cumulative probability at the start of every bin, plus a final 1.
+    cdf: Vec<f64>,
+}
+
+impl PiecewiseConstant {
+    /// Falls back to a uniform distribution if all weights are zero. Negative
+    /// and non-finite weights count as zero.
+    pub fn new(weights: &[f64]) -> Self {
+        let usable = |weight: &f64| if weight.is_finite() { weight.max(0.0) } else { 0.0 };
+        let total: f64 = weights.iter().map(usable).sum();
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        let mut accumulated = 0.0;
+        cdf.push(0.0);
+        for (index, weight) in weights.iter().enumerate() {
+            accumulated += if total > 0.0 {
+                usable(weight) / total
+            } else {
+                1.0 / weights.len() as f64
+            };
+            cdf.push(if index + 1 == weights.len() { 1.0 } else { accumulated });
+        }
+
+        PiecewiseConstant { cdf }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cdf.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn probability(&self, bin: usize) -> f64 {
+        self.cdf[bin + 1] - self.cdf[bin]
+    }
+
+    /// Density at any point of the given bin.
+    pub fn density(&self, bin: usize) -> f64 {
+        self.probability(bin) * self.len() as f64
+    }
+
+    /// Maps a uniform `u` to the chosen bin and the position within it. A NaN
+    /// `u` falls at the start of the last bin.
+    pub fn sample(&self, u: f64) -> (usize, f64) {
+        if u.is_nan() {
+            return (self.len() - 1, 0.0);
+        }
+
+        let bin = match self.cdf.binary_search_by(|value| value.partial_cmp(&u).unwrap()) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        }
+        .min(self.len() - 1);
+        // Skip over empty bins sharing the same cumulative value.
+        let bin = (bin..self.len()).find(|&bin| self.probability(bin) > 0.0).unwrap_or(bin);
+
+        let offset = (u - self.cdf[bin]) / self.probability(bin);
+        (bin, offset.clamp(0.0, 1.0 - f64::EPSILON))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    pub fn orthonormal_basis_is_orthonormal() {
+        for normal in &[Vector3::x_axis(), Vector3::z_axis(), Unit::new_normalize(Vector3::new(1.0, -2.0, 3.0))] {
+            let (tangent, bitangent) = orthonormal_basis(normal);
+
+            assert!((tangent.norm() - 1.0).abs() < 1e-12);
+            assert!((bitangent.norm() - 1.0).abs() < 1e-12);
+            assert!(tangent.dot(normal).abs() < 1e-12);
+            assert!(bitangent.dot(normal).abs() < 1e-12);
+            assert!(tangent.dot(&bitangent).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    pub fn stratified_square_covers_every_stratum() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let samples = stratified_square(18, &mut rng);
+
+        assert_eq!(samples.len(), 18);
+        for (i, j) in iproduct!(0..4, 0..4) {
+            let in_stratum = |&(x, y): &(f64, f64)| (x * 4.0) as usize == i && (y * 4.0) as usize == j;
+            assert_eq!(samples[..16].iter().filter(|s| in_stratum(s)).count(), 1);
+        }
+        assert!(samples.iter().all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y)));
+    }
+
+    #[test]
+    pub fn piecewise_constant_follows_weights() {
+        let distribution = PiecewiseConstant::new(&[1.0, 0.0, 3.0]);
+
+        assert_eq!(distribution.density(0), 0.75);
+        assert_eq!(distribution.density(1), 0.0);
+        assert_eq!(distribution.density(2), 2.25);
+        assert_eq!(distribution.sample(0.0), (0, 0.0));
+        assert_eq!(distribution.sample(0.125), (0, 0.5));
+        assert_eq!(distribution.sample(0.25), (2, 0.0));
+        assert_eq!(distribution.sample(0.625), (2, 0.5));
+    }
+
+    #[test]
+    pub fn piecewise_constant_without_weights_is_uniform() {
+        let distribution = PiecewiseConstant::new(&[0.0, 0.0]);
+
+        assert_eq!(distribution.density(1), 1.0);
+        assert_eq!(distribution.sample(0.75), (1, 0.5));
+    }
+
+    #[test]
+    pub fn piecewise_constant_ignores_non_finite_values() {
+        let distribution = PiecewiseConstant::new(&[1.0, f64::NAN, f64::INFINITY, 1.0]);
+
+        assert_eq!(distribution.density(1), 0.0);
+        assert_eq!(distribution.density(2), 0.0);
+        assert_eq!(distribution.sample(0.75), (3, 0.5));
+        assert_eq!(distribution.sample(f64::NAN), (3, 0.0));
+    }
+
+    #[test]
+    pub fn cosine_hemisphere_mean_cosine() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let normal = Unit::new_normalize(Vector3::new(1.0, 1.0, 0.0));
+        let samples = 100_000;
+
+        let mean: f64 = (0..samples)
+            .map(|_| cosine_hemisphere(&normal, &mut rng).dot(&normal))
+            .sum::<f64>()
+            / samples as f64;
+
+        // E[cos] under a cos / pi density is 2/3.
+        assert!((mean - 2.0 / 3.0).abs() < 0.01, "{}", mean);
+    }
+
+    #[test]
+    pub fn ggx_narrows_with_alpha() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let normal = Vector3::z_axis();
+        let samples = 100_000;
+        let mean_cosine = |alpha: f64, rng: &mut StdRng| {
+            (0..samples).map(|_| ggx(&normal, alpha, rng).z).sum::<f64>() / samples as f64
+        };
+
+        // With alpha = 1 the distribution is uniform and the density is cos / pi.
+        let rough = mean_cosine(1.0, &mut rng);
+        assert!((rough - 2.0 / 3.0).abs() < 0.01, "{}", rough);
+        assert!(mean_cosine(0.3, &mut rng) > rough);
+        assert_eq!(ggx(&normal, 0.0, &mut rng), normal);
+    }
+}
@@ -1,11 +1,16 @@
 use crate::color::Color;
+use crate::environment::EnvironmentMap;
 use crate::light::Light;
+use crate::radiance::Radiance;
 use crate::shapes::Shape;
 
+use nalgebra::Vector3;
+
 pub struct Scene {
     lights: Vec<Light>,
     shapes: Vec<Box<dyn Shape>>,
     background: Color,
+    environment: Option<EnvironmentMap>,
 }
 
 impl Scene {
@@ -14,6 +19,7 @@ impl Scene {
             lights: Vec::new(),
             shapes: Vec::new(),
             background,
+            environment: None,
         }
     }
 
@@ -35,7 +41,26 @@ impl Scene {
         &self.lights
     }
 
+    /// Replaces the flat background with an environment map, which also
+    /// lights the scene.
+    pub fn set_environment(&mut self, environment: EnvironmentMap) -> &mut Self {
+        self.environment = Some(environment);
+        self
+    }
+
     pub fn background(&self) -> Color {
         self.background.clone()
     }
+
+    pub fn environment(&self) -> Option<&EnvironmentMap> {
+        self.environment.as_ref()
+    }
+
+    /// Light arriving along a direction in which a ray leaves the scene.
+    pub fn background_radiance(&self, direction: &Vector3<f64>) -> Radiance {
+        match &self.environment {
+            Some(environment) => environment.radiance(direction),
+            None => Radiance::from(&self.background),
+        }
+    }
 }
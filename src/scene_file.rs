@@ -0,0 +1,1598 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::light::{Attenuation, Light, LightShape, Spotlight};
+use crate::obj::{self, ObjError};
+use crate::radiance::Radiance;
+use crate::scene::Scene;
+use crate::scene_generator;
+use crate::sky::Sky;
+use crate::textures::{
+    Bump, Checker, Filter, ImageTexture, NoisePattern, NoiseTexture, Texture, WrapMode,
+};
+use crate::shapes::{
+    Cone, Cuboid, Cylinder, Disk, Instance, Material, MetallicRoughness, Plane, Rectangle, Shape,
+    Sphere, Torus,
+};
+
+use nalgebra::{Matrix4, Point3, Rotation3, Unit, Vector3};
+use serde::Deserialize;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        file: String,
+        error: io::Error,
+    },
+    Parse {
+        file: String,
+        error: serde_yaml::Error,
+    },
+    Mesh {
+        file: String,
+        key: String,
+        error: ObjError,
+    },
+    Image {
+        file: String,
+        key: String,
+        error: io::Error,
+    },
+    Transform {
+        file: String,
+        key: String,
+    },
+    /// Value outside of the range the scene can be built with.
+    Invalid {
+        file: String,
+        key: String,
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io { file, error } => write!(f, "{}: {}", file, error),
+            SceneError::Parse { file, error } => write!(f, "{}: {}", file, error),
+            SceneError::Mesh { file, key, error } => {
+                write!(f, "{}: {}: could not load mesh: {}", file, key, error)
+            }
+            SceneError::Image { file, key, error } => {
+                write!(f, "{}: {}: could not load image: {}", file, key, error)
+            }
+            SceneError::Transform { file, key } => {
+                write!(f, "{}: {}: transformation cannot be inverted", file, key)
+            }
+            SceneError::Invalid { file, key, reason } => write!(f, "{}: {}: {}", file, key, reason),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: CameraDescription,
+    #[serde(default)]
+    background: [u8; 3],
+    environment: Option<EnvironmentDescription>,
+    sky: Option<SkyDescription>,
+    #[serde(default)]
+    lights: Vec<LightDescription>,
+    #[serde(default)]
+    shapes: Vec<ShapeDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    position: [f64; 3],
+    /// Roll, pitch and yaw, in radians.
+    #[serde(default)]
+    rotation: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentDescription {
+    /// Equirectangular Radiance `.hdr` image.
+    file: String,
+    #[serde(default = "default_intensity")]
+    intensity: f64,
+    /// Around the vertical axis, in radians.
+    #[serde(default)]
+    rotation: f64,
+    #[serde(default = "default_light_samples")]
+    samples: usize,
+}
+
+/// Physical sky replacing the background and environment, with a matching
+/// sun light. Angles are in radians.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SkyDescription {
+    elevation: f64,
+    #[serde(default)]
+    azimuth: f64,
+    #[serde(default = "default_turbidity")]
+    turbidity: f64,
+    #[serde(default = "default_intensity")]
+    intensity: f64,
+    #[serde(default = "default_intensity")]
+    sun_intensity: f64,
+    #[serde(default = "default_light_samples")]
+    samples: usize,
+}
+
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDescription {
+    /// Not needed by directional lights.
+    #[serde(default)]
+    position: [f64; 3],
+    intensity: f64,
+    /// Linear RGB, scaled by the intensity.
+    #[serde(default = "default_light_color")]
+    color: [f64; 3],
+    #[serde(default = "default_attenuation")]
+    attenuation: AttenuationDescription,
+    shape: Option<LightShapeDescription>,
+    spotlight: Option<SpotlightDescription>,
+    #[serde(default = "default_light_samples")]
+    samples: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum AttenuationDescription {
+    None,
+    InverseSquare,
+    Polynomial {
+        #[serde(default)]
+        constant: f64,
+        #[serde(default)]
+        linear: f64,
+        #[serde(default)]
+        quadratic: f64,
+    },
+}
+
+impl AttenuationDescription {
+    fn build(self) -> Attenuation {
+        match self {
+            AttenuationDescription::None => Attenuation::None,
+            AttenuationDescription::InverseSquare => Attenuation::InverseSquare,
+            AttenuationDescription::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            } => Attenuation::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            },
+        }
+    }
+}
+
+fn default_light_color() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_attenuation() -> AttenuationDescription {
+    AttenuationDescription::InverseSquare
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum LightShapeDescription {
+    Rectangle { u: [f64; 3], v: [f64; 3] },
+    Disk { normal: [f64; 3], radius: f64 },
+    Sphere { radius: f64 },
+    Directional { direction: [f64; 3] },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpotlightDescription {
+    direction: [f64; 3],
+    /// Half-angle of the cone, in radians.
+    angle: f64,
+    #[serde(default)]
+    edge: f64,
+    #[serde(default)]
+    falloff: f64,
+}
+
+impl LightShapeDescription {
+    fn build(self) -> LightShape {
+        match self {
+            LightShapeDescription::Rectangle { u, v } => LightShape::Rectangle {
+                u: vector(u),
+                v: vector(v),
+            },
+            LightShapeDescription::Disk { normal, radius } => LightShape::Disk {
+                normal: Unit::new_normalize(vector(normal)),
+                radius,
+            },
+            LightShapeDescription::Sphere { radius } => LightShape::Sphere { radius },
+            LightShapeDescription::Directional { direction } => LightShape::Directional {
+                direction: Unit::new_normalize(vector(direction)),
+            },
+        }
+    }
+}
+
+fn default_light_samples() -> usize {
+    16
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct MaterialDescription {
+    ambient_light: Option<f64>,
+    diffuse_coefficient: Option<f64>,
+    specular_coefficient: Option<f64>,
+    reflectiveness: Option<f64>,
+    roughness: Option<f64>,
+    transparency: Option<f64>,
+    refractive_index: Option<f64>,
+    texture: Option<TextureDescription>,
+    bump: Option<BumpDescription>,
+    pbr: Option<PbrDescription>,
+    emission: Option<EmissionDescription>,
+}
+
+impl MaterialDescription {
+    /// Overrides the given material with the described values. Texture files
+    /// are resolved relative to `directory`. Errors name the field of the
+    /// image that could not be loaded.
+    fn apply_to(
+        &self,
+        material: &Material,
+        directory: &Path,
+    ) -> Result<Material, (&'static str, io::Error)> {
+        let texture = match &self.texture {
+            Some(texture) => Some(texture.build(directory).map_err(|error| ("texture", error))?),
+            None => material.texture.clone(),
+        };
+        let bump = match &self.bump {
+            Some(bump) => Some(bump.build(directory).map_err(|error| ("bump", error))?),
+            None => material.bump.clone(),
+        };
+
+        Ok(Material {
+            ambient_light: self.ambient_light.unwrap_or(material.ambient_light),
+            diffuse_coefficient: self
+                .diffuse_coefficient
+                .unwrap_or(material.diffuse_coefficient),
+            specular_coefficient: self
+                .specular_coefficient
+                .unwrap_or(material.specular_coefficient),
+            reflectiveness: self.reflectiveness.unwrap_or(material.reflectiveness),
+            roughness: self.roughness.unwrap_or(material.roughness),
+            transparency: self.transparency.unwrap_or(material.transparency),
+            refractive_index: self.refractive_index.unwrap_or(material.refractive_index),
+            texture,
+            bump,
+            pbr: match &self.pbr {
+                Some(pbr) => Some(MetallicRoughness::new(pbr.metallic, pbr.roughness)),
+                None => material.pbr,
+            },
+            emission: match &self.emission {
+                Some(emission) => Radiance::from(color(emission.color)) * emission.strength,
+                None => material.emission,
+            },
+        })
+    }
+}
+
+/// glTF metallic-roughness parameters, with the same defaults.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PbrDescription {
+    #[serde(default = "default_pbr_factor")]
+    metallic: f64,
+    #[serde(default = "default_pbr_factor")]
+    roughness: f64,
+}
+
+fn default_pbr_factor() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EmissionDescription {
+    #[serde(default = "default_emission_color")]
+    color: [u8; 3],
+    #[serde(default = "default_emission_strength")]
+    strength: f64,
+}
+
+fn default_emission_color() -> [u8; 3] {
+    [255, 255, 255]
+}
+
+fn default_emission_strength() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum BumpDescription {
+    /// Height given by the luminance of any texture.
+    HeightMap {
+        texture: TextureDescription,
+        #[serde(default = "default_bump_strength")]
+        strength: f64,
+    },
+    /// Tangent-space normals stored linearly in a PNG image.
+    NormalMap {
+        file: String,
+        #[serde(default = "default_wrap")]
+        wrap: WrapDescription,
+        #[serde(default = "default_filter")]
+        filter: FilterDescription,
+        #[serde(default = "default_texture_scale")]
+        scale: [f64; 2],
+    },
+}
+
+fn default_bump_strength() -> f64 {
+    1.0
+}
+
+impl BumpDescription {
+    fn build(&self, directory: &Path) -> io::Result<Bump> {
+        match self {
+            BumpDescription::HeightMap { texture, strength } => Ok(Bump::HeightMap {
+                texture: texture.build(directory)?,
+                strength: *strength,
+            }),
+            BumpDescription::NormalMap {
+                file,
+                wrap,
+                filter,
+                scale,
+            } => {
+                let image = ImageTexture::load_linear(&directory.join(file).to_string_lossy())?;
+                Ok(Bump::NormalMap {
+                    texture: Arc::new(configure_image(image, wrap, filter, scale)),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDescription {
+    Image {
+        /// sRGB PNG image.
+        file: String,
+        #[serde(default = "default_wrap")]
+        wrap: WrapDescription,
+        #[serde(default = "default_filter")]
+        filter: FilterDescription,
+        /// Repetitions per unit of texture coordinates.
+        #[serde(default = "default_texture_scale")]
+        scale: [f64; 2],
+    },
+    Checker {
+        #[serde(default = "default_checker_colors")]
+        colors: [[u8; 3]; 2],
+        #[serde(default = "default_checker_size")]
+        size: f64,
+    },
+    Noise {
+        pattern: PatternDescription,
+        colors: [[u8; 3]; 2],
+        /// Spatial frequency.
+        #[serde(default = "default_noise_scale")]
+        scale: f64,
+        #[serde(default = "default_octaves")]
+        octaves: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PatternDescription {
+    Fbm,
+    Turbulence,
+    Marble,
+    Wood,
+    Worley,
+}
+
+/// Those of the default checkerboard.
+fn default_checker_colors() -> [[u8; 3]; 2] {
+    [[128, 128, 128], [100, 100, 100]]
+}
+
+fn default_checker_size() -> f64 {
+    1.0
+}
+
+fn default_noise_scale() -> f64 {
+    1.0
+}
+
+fn default_octaves() -> usize {
+    6
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WrapDescription {
+    Repeat,
+    MirroredRepeat,
+    Clamp,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FilterDescription {
+    Nearest,
+    Bilinear,
+}
+
+fn default_wrap() -> WrapDescription {
+    WrapDescription::Repeat
+}
+
+fn default_filter() -> FilterDescription {
+    FilterDescription::Bilinear
+}
+
+fn default_texture_scale() -> [f64; 2] {
+    [1.0, 1.0]
+}
+
+impl TextureDescription {
+    fn build(&self, directory: &Path) -> io::Result<Arc<dyn Texture>> {
+        match self {
+            TextureDescription::Image {
+                file,
+                wrap,
+                filter,
+                scale,
+            } => {
+                let image = ImageTexture::load(&directory.join(file).to_string_lossy())?;
+                Ok(Arc::new(configure_image(image, wrap, filter, scale)))
+            }
+            TextureDescription::Checker {
+                colors: [even, odd],
+                size,
+            } => {
+                let checker = Checker::new(Radiance::from(color(*even)), Radiance::from(color(*odd)));
+                Ok(Arc::new(checker.with_size(*size)))
+            }
+            TextureDescription::Noise {
+                pattern,
+                colors: [first, second],
+                scale,
+                octaves,
+                seed,
+            } => {
+                let pattern = match pattern {
+                    PatternDescription::Fbm => NoisePattern::Fbm,
+                    PatternDescription::Turbulence => NoisePattern::Turbulence,
+                    PatternDescription::Marble => NoisePattern::Marble,
+                    PatternDescription::Wood => NoisePattern::Wood,
+                    PatternDescription::Worley => NoisePattern::Worley,
+                };
+                let texture = NoiseTexture::new(
+                    pattern,
+                    Radiance::from(color(*first)),
+                    Radiance::from(color(*second)),
+                );
+                Ok(Arc::new(texture.with_scale(*scale).with_octaves(*octaves).with_seed(*seed)))
+            }
+        }
+    }
+}
+
+fn configure_image(
+    image: ImageTexture,
+    wrap: &WrapDescription,
+    filter: &FilterDescription,
+    [u, v]: &[f64; 2],
+) -> ImageTexture {
+    let wrap = match wrap {
+        WrapDescription::Repeat => WrapMode::Repeat,
+        WrapDescription::MirroredRepeat => WrapMode::MirroredRepeat,
+        WrapDescription::Clamp => WrapMode::ClampToEdge,
+    };
+    let filter = match filter {
+        FilterDescription::Nearest => Filter::Nearest,
+        FilterDescription::Bilinear => Filter::Bilinear,
+    };
+    image.with_wrap(wrap).with_filter(filter).with_scale(*u, *v)
+}
+
+fn default_mesh_color() -> [u8; 3] {
+    [200, 200, 200]
+}
+
+fn default_half_extent() -> f64 {
+    30.0
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ShapeDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    Torus {
+        center: [f64; 3],
+        major_radius: f64,
+        minor_radius: f64,
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    Cylinder {
+        base: [f64; 3],
+        radius: f64,
+        height: f64,
+        color: [u8; 3],
+        #[serde(default = "default_caps")]
+        caps: bool,
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    InfiniteCylinder {
+        point: [f64; 3],
+        radius: f64,
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    Cone {
+        base: [f64; 3],
+        radius: f64,
+        height: f64,
+        color: [u8; 3],
+        #[serde(default = "default_caps")]
+        caps: bool,
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    InfiniteCone {
+        apex: [f64; 3],
+        /// Between the sides and the axis, in radians.
+        angle: f64,
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    Checkerboard {
+        #[serde(default)]
+        height: f64,
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    Plane {
+        point: [f64; 3],
+        normal: [f64; 3],
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    Disk {
+        center: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    /// Facing along `u × v`.
+    Rectangle {
+        corner: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    Box {
+        min: [f64; 3],
+        max: [f64; 3],
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    Mesh {
+        file: String,
+        #[serde(default = "default_mesh_color")]
+        color: [u8; 3],
+        #[serde(default)]
+        material: MaterialDescription,
+    },
+    RandomSpheres {
+        count: usize,
+        #[serde(default = "default_half_extent")]
+        half_extent: f64,
+    },
+    RandomPrimitives {
+        count: usize,
+        #[serde(default = "default_half_extent")]
+        half_extent: f64,
+    },
+    /// Copies of a shape placed by each of the transformations, all sharing
+    /// its geometry.
+    Instances {
+        shape: Box<ShapeDescription>,
+        transforms: Vec<TransformDescription>,
+    },
+}
+
+fn default_caps() -> bool {
+    true
+}
+
+/// Affine transformation applying a `matrix` (the top three rows of a
+/// homogeneous matrix), then `scale`, `rotation` (roll, pitch and yaw, in
+/// radians) and `translation`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDescription {
+    #[serde(default)]
+    translation: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3],
+    #[serde(default = "default_scale")]
+    scale: [f64; 3],
+    matrix: Option<[[f64; 4]; 3]>,
+}
+
+fn default_scale() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+impl TransformDescription {
+    fn build(&self) -> Matrix4<f64> {
+        let [roll, pitch, yaw] = self.rotation;
+        let matrix = match self.matrix {
+            Some(rows) => {
+                let mut matrix = Matrix4::identity();
+                for (i, row) in rows.iter().enumerate() {
+                    for (j, value) in row.iter().enumerate() {
+                        matrix[(i, j)] = *value;
+                    }
+                }
+                matrix
+            }
+            None => Matrix4::identity(),
+        };
+
+        Matrix4::new_translation(&vector(self.translation))
+            * Rotation3::from_euler_angles(roll, pitch, yaw).to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&vector(self.scale))
+            * matrix
+    }
+}
+
+fn point([x, y, z]: [f64; 3]) -> Point3<f64> {
+    Point3::new(x, y, z)
+}
+
+fn vector([x, y, z]: [f64; 3]) -> Vector3<f64> {
+    Vector3::new(x, y, z)
+}
+
+fn color([r, g, b]: [u8; 3]) -> Color {
+    Color::rgb(r, g, b)
+}
+
+/// Loads a YAML scene description. Paths of meshes are resolved relative to
+/// the directory containing the scene file.
+pub fn load(filename: &str) -> Result<(Scene, Camera), SceneError> {
+    let source = fs::read_to_string(filename).map_err(|error| SceneError::Io {
+        file: String::from(filename),
+        error,
+    })?;
+
+    parse(&source, filename)
+}
+
+fn parse(source: &str, filename: &str) -> Result<(Scene, Camera), SceneError> {
+    let description: SceneDescription =
+        serde_yaml::from_str(source).map_err(|error| SceneError::Parse {
+            file: String::from(filename),
+            error,
+        })?;
+    let directory = Path::new(filename).parent().unwrap_or_else(|| Path::new(""));
+
+    let mut camera = Camera::new(point(description.camera.position));
+    let [roll, pitch, yaw] = description.camera.rotation;
+    camera.rotate(roll, pitch, yaw);
+
+    let invalid = |key: &str, reason| SceneError::Invalid {
+        file: String::from(filename),
+        key: String::from(key),
+        reason,
+    };
+    if description.environment.is_some() && description.sky.is_some() {
+        return Err(invalid("sky", "cannot be combined with an environment"));
+    }
+
+    let mut scene = Scene::new(color(description.background));
+    if let Some(environment) = description.environment {
+        let path = directory.join(&environment.file);
+        let map = EnvironmentMap::load(&path.to_string_lossy()).map_err(|error| SceneError::Image {
+            file: String::from(filename),
+            key: String::from("environment.file"),
+            error,
+        })?;
+        scene.set_environment(
+            map.with_intensity(environment.intensity)
+                .with_rotation(environment.rotation)
+                .with_samples(environment.samples),
+        );
+    }
+    if let Some(sky) = description.sky {
+        // The Preetham model is only fitted to this range of turbidities.
+        if !(2.0..=10.0).contains(&sky.turbidity) {
+            return Err(invalid("sky.turbidity", "must be between 2 and 10"));
+        }
+        let model = Sky::new(sky.elevation, sky.azimuth, sky.turbidity);
+        scene.set_environment(
+            model
+                .to_environment(512, 256)
+                .with_intensity(sky.intensity)
+                .with_samples(sky.samples),
+        );
+        scene.add_light(model.sun_light(sky.sun_intensity));
+    }
+    for light in description.lights {
+        let [r, g, b] = light.color;
+        let mut built = Light::new(point(light.position), light.intensity)
+            .with_color(Radiance::new(r, g, b))
+            .with_attenuation(light.attenuation.build());
+        if let Some(shape) = light.shape {
+            built = built.with_shape(shape.build(), light.samples);
+        }
+        if let Some(spot) = light.spotlight {
+            let direction = Unit::new_normalize(vector(spot.direction));
+            built = built.with_spotlight(
+                Spotlight::new(direction, spot.angle)
+                    .with_edge(spot.edge)
+                    .with_falloff(spot.falloff),
+            );
+        }
+        scene.add_light(built);
+    }
+
+    for (index, shape) in description.shapes.into_iter().enumerate() {
+        let key = format!("shapes[{}]", index);
+        for shape in build_shapes(shape, directory, filename, &key)? {
+            scene.add_shape(shape);
+        }
+    }
+
+    Ok((scene, camera))
+}
+
+fn build_shapes(
+    shape: ShapeDescription,
+    directory: &Path,
+    filename: &str,
+    key: &str,
+) -> Result<Vec<Box<dyn Shape>>, SceneError> {
+    let invalid = |field: &str, reason| SceneError::Invalid {
+        file: String::from(filename),
+        key: format!("{}.{}", key, field),
+        reason,
+    };
+    let check = |valid: bool, field: &str, reason| {
+        if valid {
+            Ok(())
+        } else {
+            Err(invalid(field, reason))
+        }
+    };
+    let direction = |value: [f64; 3], field: &str| {
+        Unit::try_new(vector(value), 0.0).ok_or_else(|| invalid(field, "must not be zero"))
+    };
+
+    let shapes: Vec<Box<dyn Shape>> = match shape {
+        ShapeDescription::Sphere {
+            center,
+            radius,
+            color: sphere_color,
+            material,
+        } => {
+            check(radius >= 0.0, "sphere.radius", "must not be negative")?;
+            let sphere = Sphere::new(point(center), radius, color(sphere_color));
+            with_material(
+                sphere,
+                Sphere::with_material,
+                &material,
+                "sphere",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Torus {
+            center,
+            major_radius,
+            minor_radius,
+            color: torus_color,
+            material,
+        } => {
+            check(major_radius >= 0.0, "torus.major_radius", "must not be negative")?;
+            check(minor_radius > 0.0, "torus.minor_radius", "must be positive")?;
+            let torus = Torus::new(point(center), major_radius, minor_radius, color(torus_color));
+            with_material(
+                torus,
+                Torus::with_material,
+                &material,
+                "torus",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Cylinder {
+            base,
+            radius,
+            height,
+            color: cylinder_color,
+            caps,
+            material,
+        } => {
+            check(radius >= 0.0, "cylinder.radius", "must not be negative")?;
+            check(height > 0.0, "cylinder.height", "must be positive")?;
+            let mut cylinder = Cylinder::new(point(base), radius, height, color(cylinder_color));
+            if caps {
+                cylinder = cylinder.with_caps();
+            }
+            with_material(
+                cylinder,
+                Cylinder::with_material,
+                &material,
+                "cylinder",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::InfiniteCylinder {
+            point: axis_point,
+            radius,
+            color: cylinder_color,
+            material,
+        } => {
+            check(radius >= 0.0, "infinite_cylinder.radius", "must not be negative")?;
+            let cylinder = Cylinder::infinite(point(axis_point), radius, color(cylinder_color));
+            with_material(
+                cylinder,
+                Cylinder::with_material,
+                &material,
+                "infinite_cylinder",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Cone {
+            base,
+            radius,
+            height,
+            color: cone_color,
+            caps,
+            material,
+        } => {
+            check(radius >= 0.0, "cone.radius", "must not be negative")?;
+            check(height > 0.0, "cone.height", "must be positive")?;
+            let mut cone = Cone::new(point(base), radius, height, color(cone_color));
+            if caps {
+                cone = cone.with_caps();
+            }
+            with_material(
+                cone,
+                Cone::with_material,
+                &material,
+                "cone",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::InfiniteCone {
+            apex,
+            angle,
+            color: cone_color,
+            material,
+        } => {
+            let cone = Cone::infinite(point(apex), angle, color(cone_color));
+            with_material(
+                cone,
+                Cone::with_material,
+                &material,
+                "infinite_cone",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Checkerboard { height, material } => {
+            let board = Plane::checkerboard(height);
+            with_material(
+                board,
+                Plane::with_material,
+                &material,
+                "checkerboard",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Plane {
+            point: origin,
+            normal,
+            color: plane_color,
+            material,
+        } => {
+            let normal = direction(normal, "plane.normal")?;
+            let plane = Plane::new(point(origin), normal, color(plane_color));
+            with_material(
+                plane,
+                Plane::with_material,
+                &material,
+                "plane",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Disk {
+            center,
+            normal,
+            radius,
+            color: disk_color,
+            material,
+        } => {
+            let normal = direction(normal, "disk.normal")?;
+            check(radius >= 0.0, "disk.radius", "must not be negative")?;
+            let disk = Disk::new(point(center), normal, radius, color(disk_color));
+            with_material(
+                disk,
+                Disk::with_material,
+                &material,
+                "disk",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Rectangle {
+            corner,
+            u,
+            v,
+            color: rectangle_color,
+            material,
+        } => {
+            let (u, v) = (vector(u), vector(v));
+            check(u.norm() > 0.0, "rectangle.u", "must not be zero")?;
+            check(u.cross(&v).norm() > 0.0, "rectangle.v", "must not be zero or parallel to u")?;
+            let rectangle = Rectangle::new(point(corner), u, v, color(rectangle_color));
+            with_material(
+                rectangle,
+                Rectangle::with_material,
+                &material,
+                "rectangle",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Box {
+            min,
+            max,
+            color: box_color,
+            material,
+        } => {
+            let cuboid = Cuboid::new(point(min), point(max), color(box_color));
+            with_material(
+                cuboid,
+                Cuboid::with_material,
+                &material,
+                "box",
+                directory,
+                filename,
+                key,
+            )?
+        }
+        ShapeDescription::Mesh {
+            file,
+            color: mesh_color,
+            material,
+        } => {
+            let path = directory.join(file);
+            let material = build_material(
+                &material,
+                &Material::default(),
+                "mesh",
+                directory,
+                filename,
+                key,
+            )?;
+            let mesh = obj::load(&path.to_string_lossy(), color(mesh_color), material).map_err(
+                |error| SceneError::Mesh {
+                    file: String::from(filename),
+                    key: format!("{}.mesh", key),
+                    error,
+                },
+            )?;
+            vec![Box::new(mesh)]
+        }
+        ShapeDescription::RandomSpheres { count, half_extent } => {
+            scene_generator::random_spheres(count, half_extent)
+                .into_iter()
+                .map(|sphere| Box::new(sphere) as Box<dyn Shape>)
+                .collect()
+        }
+        ShapeDescription::RandomPrimitives { count, half_extent } => {
+            scene_generator::random_primitives(count, half_extent)
+        }
+        ShapeDescription::Instances { shape, transforms } => {
+            let shape_key = format!("{}.instances.shape", key);
+            let geometry: Vec<Arc<dyn Shape>> =
+                build_shapes(*shape, directory, filename, &shape_key)?
+                    .into_iter()
+                    .map(Arc::from)
+                    .collect();
+            let mut instances: Vec<Box<dyn Shape>> = Vec::new();
+            for (index, transform) in transforms.iter().enumerate() {
+                for shape in &geometry {
+                    let instance = Instance::new(shape.clone(), transform.build()).ok_or_else(|| {
+                        SceneError::Transform {
+                            file: String::from(filename),
+                            key: format!("{}.instances.transforms[{}]", key, index),
+                        }
+                    })?;
+                    instances.push(Box::new(instance));
+                }
+            }
+            instances
+        }
+    };
+
+    Ok(shapes)
+}
+
+/// Overrides `base` with the material described for a shape of the given
+/// kind. Errors name the image field that could not be loaded.
+fn build_material(
+    description: &MaterialDescription,
+    base: &Material,
+    kind: &str,
+    directory: &Path,
+    filename: &str,
+    key: &str,
+) -> Result<Material, SceneError> {
+    description
+        .apply_to(base, directory)
+        .map_err(|(field, error)| SceneError::Image {
+            file: String::from(filename),
+            key: format!("{}.{}.material.{}", key, kind, field),
+            error,
+        })
+}
+
+/// Gives a newly built shape its described material, on top of the shape's
+/// default one.
+fn with_material<S: Shape + 'static>(
+    shape: S,
+    set_material: fn(S, Material) -> S,
+    description: &MaterialDescription,
+    kind: &str,
+    directory: &Path,
+    filename: &str,
+    key: &str,
+) -> Result<Vec<Box<dyn Shape>>, SceneError> {
+    let material = build_material(description, shape.material(), kind, directory, filename, key)?;
+    Ok(vec![Box::new(set_material(shape, material))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Line3d;
+
+    fn parse_error(source: &str) -> String {
+        match parse(source, "test.yml") {
+            Err(error) => error.to_string(),
+            Ok(_) => panic!("Expected an error"),
+        }
+    }
+
+    #[test]
+    pub fn parse_scene() {
+        let source = "\
+camera:
+  position: [-5, 0, 2]
+  rotation: [1, 0, 0]
+background: [10, 20, 30]
+lights:
+  - position: [-5, 5, 7]
+    intensity: 1.0
+shapes:
+  - checkerboard:
+      material:
+        reflectiveness: 0.5
+        roughness: 0.2
+  - sphere:
+      center: [0, 0, 1]
+      radius: 1
+      color: [255, 0, 0]
+      material:
+        texture:
+          noise:
+            pattern: marble
+            colors: [[255, 255, 255], [20, 20, 20]]
+            scale: 2
+        pbr:
+          roughness: 0.4
+        emission:
+          color: [255, 0, 0]
+          strength: 2
+  - torus:
+      center: [2, 0, 0.5]
+      major_radius: 1
+      minor_radius: 0.5
+      color: [0, 0, 255]
+";
+
+        let (scene, camera) = parse(source, "test.yml").unwrap();
+
+        assert_eq!(*camera.center(), Point3::new(-5.0, 0.0, 2.0));
+        assert_eq!(scene.background().b, 30);
+        assert_eq!(scene.lights().len(), 1);
+        assert_eq!(scene.shapes().len(), 3);
+        assert_eq!(scene.shapes()[0].material().reflectiveness, 0.5);
+        assert_eq!(scene.shapes()[0].material().roughness, 0.2);
+        assert_eq!(scene.shapes()[0].material().diffuse_coefficient, 0.6);
+        assert!(scene.shapes()[0].material().texture.is_some());
+        assert!(scene.shapes()[1].material().texture.is_some());
+        assert_eq!(scene.shapes()[0].material().pbr, None);
+        assert_eq!(scene.shapes()[1].material().pbr, Some(MetallicRoughness::new(1.0, 0.4)));
+        assert_eq!(scene.shapes()[0].material().reflection_roughness(), 0.2);
+        assert_eq!(scene.shapes()[1].material().reflection_roughness(), 0.4);
+        assert_eq!(scene.shapes()[1].material().emission, Radiance::new(2.0, 0.0, 0.0));
+        assert_eq!(scene.shapes()[2].color_at(&Point3::new(3.5, 0.0, 0.5)).b, 1.0);
+    }
+
+    #[test]
+    pub fn parse_primitives() {
+        let source = "\
+camera:
+  position: [-5, 0, 2]
+shapes:
+  - cylinder:
+      base: [0, 0, 0]
+      radius: 1
+      height: 2
+      color: [255, 0, 0]
+      caps: false
+  - infinite_cone:
+      apex: [0, 3, 0]
+      angle: 0.3
+      color: [0, 255, 0]
+  - random_primitives:
+      count: 5
+      half_extent: 10
+";
+
+        let (scene, _) = parse(source, "test.yml").unwrap();
+
+        assert_eq!(scene.shapes().len(), 7);
+        assert!(scene.shapes()[0].bounding_box().is_some());
+        assert!(scene.shapes()[1].bounding_box().is_none());
+        // Looking down the open tube misses it.
+        let ray = Line3d::new(Point3::new(0.0, 0.0, 5.0), -Vector3::z_axis());
+        assert!(scene.shapes()[0].intersect(&ray).is_empty());
+    }
+
+    #[test]
+    pub fn parse_reports_thin_torus() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - torus:
+      center: [0, 0, 0]
+      major_radius: 1
+      minor_radius: 0
+      color: [255, 255, 255]
+";
+
+        assert_eq!(parse_error(source), "test.yml: shapes[0].torus.minor_radius: must be positive");
+    }
+
+    #[test]
+    pub fn parse_reports_flat_cone() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - cone:
+      base: [0, 0, 0]
+      radius: 1
+      height: 0
+      color: [255, 255, 255]
+";
+
+        assert_eq!(parse_error(source), "test.yml: shapes[0].cone.height: must be positive");
+    }
+
+    #[test]
+    pub fn parse_flat_shapes() {
+        let source = "\
+camera:
+  position: [-5, 0, 2]
+shapes:
+  - plane:
+      point: [3, 0, 0]
+      normal: [-1, 0, 0]
+      color: [200, 200, 200]
+      material:
+        texture:
+          checker:
+            size: 0.5
+  - disk:
+      center: [0, 0, 4]
+      normal: [0, 0, -1]
+      radius: 0.5
+      color: [255, 255, 255]
+      material:
+        emission:
+          strength: 5
+  - rectangle:
+      corner: [-1, -1, 0]
+      u: [2, 0, 0]
+      v: [0, 2, 0]
+      color: [255, 255, 255]
+  - box:
+      min: [0, 0, 0]
+      max: [1, 1, 1]
+      color: [255, 0, 0]
+";
+
+        let (scene, _) = parse(source, "test.yml").unwrap();
+
+        assert_eq!(scene.shapes().len(), 4);
+        let wall = &scene.shapes()[0];
+        assert_eq!(wall.normal_at(&Point3::new(3.0, 0.0, 0.0)), -Vector3::x_axis());
+        assert_eq!(wall.color_at(&Point3::new(3.0, -0.25, 0.25)), Radiance::from(color([128; 3])));
+        assert_eq!(wall.color_at(&Point3::new(3.0, -0.25, 0.75)), Radiance::from(color([100; 3])));
+        assert_eq!(scene.shapes()[1].material().emission, Radiance::gray(5.0));
+        assert!(scene.shapes()[3].bounding_box().is_some());
+    }
+
+    #[test]
+    pub fn parse_reports_degenerate_flat_shapes() {
+        let zero_normal = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - disk:
+      center: [0, 0, 0]
+      normal: [0, 0, 0]
+      radius: 1
+      color: [255, 255, 255]
+";
+        let negative_radius = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - disk:
+      center: [0, 0, 0]
+      normal: [0, 0, 1]
+      radius: -1
+      color: [255, 255, 255]
+";
+        let parallel = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - rectangle:
+      corner: [0, 0, 0]
+      u: [1, 0, 0]
+      v: [2, 0, 0]
+      color: [255, 255, 255]
+";
+
+        assert_eq!(parse_error(zero_normal), "test.yml: shapes[0].disk.normal: must not be zero");
+        assert_eq!(
+            parse_error(negative_radius),
+            "test.yml: shapes[0].disk.radius: must not be negative"
+        );
+        assert_eq!(
+            parse_error(parallel),
+            "test.yml: shapes[0].rectangle.v: must not be zero or parallel to u"
+        );
+    }
+
+    #[test]
+    pub fn parse_instances() {
+        let source = "\
+camera:
+  position: [-5, 0, 2]
+shapes:
+  - instances:
+      shape:
+        box:
+          min: [-1, -1, -1]
+          max: [1, 1, 1]
+          color: [255, 0, 0]
+      transforms:
+        - translation: [0, 0, 1]
+        - translation: [0, 5, 1]
+          rotation: [0, 0, 0.5]
+          scale: [1, 2, 1]
+        - matrix: [[1, 0.5, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0]]
+";
+
+        let (scene, _) = parse(source, "test.yml").unwrap();
+
+        assert_eq!(scene.shapes().len(), 3);
+        let bounds = scene.shapes()[0].bounding_box().unwrap();
+        assert_eq!(*bounds.max(), Point3::new(1.0, 1.0, 2.0));
+        // Sheared along X by half of Y.
+        let sheared = scene.shapes()[2].bounding_box().unwrap();
+        assert_eq!(*sheared.max(), Point3::new(1.5, 1.0, 1.0));
+    }
+
+    #[test]
+    pub fn parse_reports_singular_transform() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - sphere:
+      center: [0, 0, 0]
+      radius: 1
+      color: [255, 255, 255]
+  - instances:
+      shape:
+        sphere:
+          center: [0, 0, 0]
+          radius: 1
+          color: [255, 255, 255]
+      transforms:
+        - translation: [1, 0, 0]
+        - scale: [1, 0, 1]
+";
+
+        let error = parse_error(source);
+
+        assert_eq!(
+            error,
+            "test.yml: shapes[1].instances.transforms[1]: transformation cannot be inverted"
+        );
+    }
+
+    #[test]
+    pub fn parse_lights() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+lights:
+  - position: [0, 0, 5]
+    intensity: 1.0
+    shape:
+      disk:
+        normal: [0, 0, -1]
+        radius: 0.5
+    samples: 9
+  - position: [0, 0, 5]
+    intensity: 10
+    color: [1.0, 0.8, 0.6]
+    attenuation: none
+  - intensity: 0.5
+    shape:
+      directional:
+        direction: [1, 0, -1]
+  - position: [0, 0, 5]
+    intensity: 1
+    spotlight:
+      direction: [0, 0, -1]
+      angle: 0.5
+      edge: 0.2
+";
+
+        let (scene, _) = parse(source, "test.yml").unwrap();
+
+        let light = &scene.lights()[0];
+        assert_eq!(light.samples, 9);
+        assert!(matches!(light.shape, LightShape::Disk { radius, .. } if radius == 0.5));
+        assert_eq!(scene.lights()[1].color, Radiance::new(1.0, 0.8, 0.6));
+        assert_eq!(light.attenuation, Attenuation::InverseSquare);
+        assert_eq!(scene.lights()[1].attenuation, Attenuation::None);
+        assert!(matches!(scene.lights()[2].shape, LightShape::Directional { .. }));
+        assert_eq!(scene.lights()[3].spotlight.map(|spot| spot.angle), Some(0.5));
+    }
+
+    #[test]
+    pub fn parse_reports_offending_key() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - sphere:
+      center: [0, 0, 1]
+      radius: large
+      color: [255, 0, 0]
+";
+
+        let error = parse_error(source);
+
+        assert!(error.starts_with("test.yml: shapes[0].sphere.radius"), "{}", error);
+        assert!(error.contains("line 6"), "{}", error);
+    }
+
+    #[test]
+    pub fn parse_reports_negative_radius() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - sphere:
+      center: [0, 0, 1]
+      radius: -1
+      color: [255, 0, 0]
+";
+
+        assert_eq!(parse_error(source), "test.yml: shapes[0].sphere.radius: must not be negative");
+    }
+
+    #[test]
+    pub fn parse_sky() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+sky:
+  elevation: 0.4
+  turbidity: 4
+";
+
+        let (scene, _) = parse(source, "test.yml").unwrap();
+
+        assert!(scene.environment().is_some());
+        assert_eq!(scene.lights().len(), 1);
+        assert!(matches!(scene.lights()[0].shape, LightShape::Directional { .. }));
+    }
+
+    #[test]
+    pub fn parse_reports_invalid_sky() {
+        let with_environment = "\
+camera:
+  position: [0, 0, 0]
+environment:
+  file: sky.hdr
+sky:
+  elevation: 0.4
+";
+        let hazy = "\
+camera:
+  position: [0, 0, 0]
+sky:
+  elevation: 0.4
+  turbidity: 20
+";
+
+        assert_eq!(
+            parse_error(with_environment),
+            "test.yml: sky: cannot be combined with an environment"
+        );
+        assert_eq!(parse_error(hazy), "test.yml: sky.turbidity: must be between 2 and 10");
+    }
+
+    #[test]
+    pub fn parse_reports_missing_environment() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+environment:
+  file: missing.hdr
+";
+
+        let error = parse_error(source);
+
+        assert!(
+            error.starts_with("test.yml: environment.file: could not load image"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    pub fn parse_reports_missing_texture() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - checkerboard:
+      material:
+        texture:
+          image:
+            file: missing.png
+            wrap: mirrored_repeat
+";
+
+        let error = parse_error(source);
+
+        assert!(
+            error.starts_with("test.yml: shapes[0].checkerboard.material.texture: could not load image"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    pub fn parse_reports_missing_normal_map() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+shapes:
+  - sphere:
+      center: [0, 0, 0]
+      radius: 1
+      color: [255, 255, 255]
+      material:
+        bump:
+          normal_map:
+            file: missing.png
+";
+
+        let error = parse_error(source);
+
+        assert!(
+            error.starts_with("test.yml: shapes[0].sphere.material.bump: could not load image"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    pub fn parse_rejects_unknown_keys() {
+        let source = "\
+camera:
+  position: [0, 0, 0]
+  fov: 90
+";
+
+        let error = parse_error(source);
+
+        assert!(error.contains("camera: unknown field `fov`"), "{}", error);
+        assert!(error.contains("line 3"), "{}", error);
+    }
+}
@@ -1,19 +1,23 @@
 use crate::color::Color;
-use crate::light::Light;
+use crate::light::{Attenuation, Light};
 use crate::progress_bar::ProgressBar;
 use crate::scene::Scene;
-use crate::shapes::{CheckerBoard, Sphere};
+use crate::shapes::{Cone, Cylinder, Plane, Shape, Sphere};
 
-use nalgebra::Point3;
+use std::collections::HashMap;
+
+use nalgebra::{Point3, Vector3};
 use rand::distributions::{Distribution, Uniform};
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
+
+const MAX_RADIUS: f64 = 0.5;
 
-pub fn random_sphere() -> Sphere {
+pub fn random_sphere(half_extent: f64) -> Sphere {
     let mut rng = thread_rng();
-    let radius_range = Uniform::new(0.1, 0.5);
+    let radius_range = Uniform::new(0.1, MAX_RADIUS);
     let radius = radius_range.sample(&mut rng);
-    let position_range_x = Uniform::new(-30.0, 30.0);
-    let position_range_y = Uniform::new(-30.0, 30.0);
+    let position_range_x = Uniform::new(-half_extent, half_extent);
+    let position_range_y = Uniform::new(-half_extent, half_extent);
     let (x, y) = (
         position_range_x.sample(&mut rng),
         position_range_y.sample(&mut rng),
@@ -23,29 +27,77 @@ pub fn random_sphere() -> Sphere {
 }
 
 pub fn spheres_demo(number_of_spheres: usize) -> Scene {
+    spheres_demo_in_area(number_of_spheres, 30.0)
+}
+
+/// Same as `spheres_demo`, but spheres are scattered over a square of the
+/// given half-width, so that large numbers of them can fit on the floor.
+pub fn spheres_demo_in_area(number_of_spheres: usize, half_extent: f64) -> Scene {
     let mut scene = Scene::new(Color::gray(0));
     scene
-        .add_light(Light::new(Point3::new(-5.0, 5.0, 7.0), 1.0))
-        .add_light(Light::new(Point3::new(-5.0, -5.0, 3.0), 0.8))
-        .add_shape(Box::new(CheckerBoard::new(0.0)));
+        .add_light(
+            Light::new(Point3::new(-5.0, 5.0, 7.0), 1.0).with_attenuation(Attenuation::None),
+        )
+        .add_light(
+            Light::new(Point3::new(-5.0, -5.0, 3.0), 0.8).with_attenuation(Attenuation::None),
+        )
+        .add_shape(Box::new(Plane::checkerboard(0.0)));
+
+    for sphere in random_spheres(number_of_spheres, half_extent) {
+        scene.add_shape(Box::new(sphere));
+    }
+
+    scene
+}
 
+/// Scatters non-overlapping spheres of random sizes and colors on the floor.
+pub fn random_spheres(number_of_spheres: usize, half_extent: f64) -> Vec<Sphere> {
     let progress_bar = ProgressBar::new("Generating scene", number_of_spheres);
-    let mut spheres = Vec::new();
+    let mut spheres: Vec<Sphere> = Vec::new();
+    // Spheres are bucketed into a grid of cells as wide as the largest
+    // sphere, so only the neighbouring cells need to be checked for collisions.
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    let cell_of = |sphere: &Sphere| {
+        let center = sphere.center();
+        (
+            (center.x / (2.0 * MAX_RADIUS)).floor() as i64,
+            (center.y / (2.0 * MAX_RADIUS)).floor() as i64,
+        )
+    };
+
     while spheres.len() < number_of_spheres {
-        let new_sphere = random_sphere();
-        if spheres
-            .iter()
-            .any(|sphere| new_sphere.collides_with(sphere))
+        let new_sphere = random_sphere(half_extent);
+        let (cell_x, cell_y) = cell_of(&new_sphere);
+        if iproduct!(cell_x - 1..=cell_x + 1, cell_y - 1..=cell_y + 1)
+            .flat_map(|cell| grid.get(&cell))
+            .flatten()
+            .any(|&index| new_sphere.collides_with(&spheres[index]))
         {
             continue;
         }
+        grid.entry((cell_x, cell_y)).or_default().push(spheres.len());
         spheres.push(new_sphere);
         progress_bar.step().print();
     }
 
-    for sphere in spheres {
-        scene.add_shape(Box::new(sphere));
-    }
+    spheres
+}
 
-    scene
+/// Scatters non-overlapping spheres, capped cylinders and cones of random
+/// sizes and colors on the floor. Cylinders and cones fill the box of the
+/// sphere they replace.
+pub fn random_primitives(count: usize, half_extent: f64) -> Vec<Box<dyn Shape>> {
+    let mut rng = thread_rng();
+    random_spheres(count, half_extent)
+        .into_iter()
+        .map(|sphere| {
+            let radius = sphere.radius();
+            let base = sphere.center() - Vector3::new(0.0, 0.0, radius);
+            match rng.gen_range(0, 3) {
+                0 => Box::new(sphere) as Box<dyn Shape>,
+                1 => Box::new(Cylinder::new(base, radius, 2.0 * radius, Color::random()).with_caps()),
+                _ => Box::new(Cone::new(base, radius, 2.0 * radius, Color::random()).with_caps()),
+            }
+        })
+        .collect()
 }
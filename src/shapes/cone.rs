@@ -0,0 +1,225 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::geometry::{BoundingBox, Line3d};
+use crate::math::Polynomial;
+use crate::radiance::Radiance;
+use crate::shapes::{Material, Shape};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Cone around a vertical axis, with its radius growing by `slope` per unit
+/// of distance below the apex. Finite cones are open below unless given an
+/// end cap.
+pub struct Cone {
+    color: Color,
+    apex: Point3<f64>,
+    slope: f64,
+    /// `None` for a double cone extending infinitely above and below the
+    /// apex.
+    height: Option<f64>,
+    capped: bool,
+    material: Material,
+}
+
+impl Cone {
+    /// Cone standing on a disk of the given radius centered on `base`, with
+    /// its apex `height` above it. The height must be positive.
+    pub fn new(base: Point3<f64>, radius: f64, height: f64, color: Color) -> Self {
+        Cone {
+            color,
+            apex: base + Vector3::new(0.0, 0.0, height),
+            slope: radius / height,
+            height: Some(height),
+            capped: false,
+            material: Material::default(),
+        }
+    }
+
+    /// Double cone of infinite extent, whose sides make `angle` (in
+    /// radians) with the vertical axis through `apex`.
+    pub fn infinite(apex: Point3<f64>, angle: f64, color: Color) -> Self {
+        Cone {
+            color,
+            apex,
+            slope: angle.tan(),
+            height: None,
+            capped: false,
+            material: Material::default(),
+        }
+    }
+
+    /// Closes the base of a finite cone with a disk.
+    pub fn with_caps(mut self) -> Self {
+        self.capped = true;
+        self
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Whether a point on the surface, given relative to the apex, lies on
+    /// the cap rather than on the side.
+    fn on_cap(&self, offset: &Vector3<f64>) -> bool {
+        match self.height.filter(|_| self.capped) {
+            Some(height) => {
+                let side_distance = (offset.x.hypot(offset.y) + self.slope * offset.z).abs()
+                    / self.slope.hypot(1.0);
+                (offset.z + height).abs() < side_distance
+            }
+            None => false,
+        }
+    }
+}
+
+impl Shape for Cone {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        let origin = ray.origin() - self.apex;
+        let direction = ray.direction();
+        let slope2 = self.slope * self.slope;
+        let a = direction.x * direction.x + direction.y * direction.y
+            - slope2 * direction.z * direction.z;
+        let b = 2.0
+            * (origin.x * direction.x + origin.y * direction.y - slope2 * origin.z * direction.z);
+        let c = origin.x * origin.x + origin.y * origin.y - slope2 * origin.z * origin.z;
+
+        let mut distances: Vec<f64> = Polynomial::new(vec![c, b, a])
+            .into_solutions()
+            .into_iter()
+            .filter(|d| match self.height {
+                Some(height) => (-height..=0.0).contains(&(origin.z + d * direction.z)),
+                None => true,
+            })
+            .collect();
+        if let Some(height) = self.height.filter(|_| self.capped && direction.z != 0.0) {
+            let d = (-height - origin.z) / direction.z;
+            let hit = origin + direction * d;
+            let radius = self.slope * height;
+            if hit.x * hit.x + hit.y * hit.y <= radius * radius {
+                distances.push(d);
+            }
+        }
+
+        distances
+            .into_iter()
+            .map(|d| (self as &dyn Shape, ray.at(d)))
+            .collect()
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.color)
+    }
+
+    /// Angle around the axis and the fraction of the height from the base to
+    /// the apex on the side (or the height above the apex on infinite
+    /// cones), and planar X and Y coordinates on the cap.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        let offset = position - self.apex;
+        if self.on_cap(&offset) {
+            return Point2::new(offset.x, offset.y);
+        }
+
+        let u = (offset.y.atan2(offset.x) / (2.0 * PI)).rem_euclid(1.0);
+        let v = match self.height {
+            Some(height) => 1.0 + offset.z / height,
+            None => offset.z,
+        };
+        Point2::new(u, v)
+    }
+
+    fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        let offset = position - self.apex;
+        if self.on_cap(&offset) {
+            return -Vector3::z_axis();
+        }
+
+        let gradient = Vector3::new(offset.x, offset.y, -self.slope * self.slope * offset.z);
+        match gradient.try_normalize(1e-12) {
+            Some(normal) => Unit::new_unchecked(normal),
+            None => Vector3::z_axis(),
+        }
+    }
+
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64> {
+        let offset = position - self.apex;
+        if self.on_cap(&offset) {
+            Vector3::x()
+        } else {
+            Vector3::new(-offset.y, offset.x, 0.0)
+        }
+    }
+
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        Point3::from(position - self.apex)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let height = self.height?;
+        let radius = self.slope * height;
+        Some(BoundingBox::new(
+            self.apex - Vector3::new(radius, radius, height),
+            self.apex + Vector3::new(radius, radius, 0.0),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::test_utils::distances;
+
+    fn cone() -> Cone {
+        Cone::new(Point3::origin(), 1.0, 2.0, Color::gray(255))
+    }
+
+    #[test]
+    pub fn ray_crosses_side_at_half_radius() {
+        let ray = Line3d::new(Point3::new(-5.0, 0.0, 1.0), Vector3::x_axis());
+
+        assert_eq!(distances(&cone(), &ray), vec![4.5, 5.5]);
+    }
+
+    #[test]
+    pub fn normal_leans_away_from_apex() {
+        let normal = cone().normal_at(&Point3::new(0.5, 0.0, 1.0));
+
+        let expected = Vector3::new(2.0, 0.0, 1.0).normalize();
+        assert!((normal.into_inner() - expected).norm() < 1e-12, "{}", normal.into_inner());
+    }
+
+    #[test]
+    pub fn cap_closes_the_base() {
+        let ray = Line3d::new(Point3::new(0.2, 0.0, -5.0), Vector3::z_axis());
+
+        assert_eq!(distances(&cone(), &ray).len(), 1);
+        let capped = cone().with_caps();
+        assert_eq!(distances(&capped, &ray).len(), 2);
+        assert_eq!(distances(&capped, &ray)[0], 5.0);
+        assert_eq!(capped.normal_at(&Point3::new(0.2, 0.0, 0.0)), -Vector3::z_axis());
+    }
+
+    #[test]
+    pub fn infinite_cone_has_two_nappes() {
+        let cone = Cone::infinite(Point3::origin(), PI / 4.0, Color::gray(255));
+        let ray = Line3d::new(Point3::new(1.0, 0.0, -5.0), Vector3::z_axis());
+
+        let distances = distances(&cone, &ray);
+
+        assert_eq!(distances.len(), 2);
+        assert!((distances[0] - 4.0).abs() < 1e-9 && (distances[1] - 6.0).abs() < 1e-9);
+        assert!(cone.bounding_box().is_none());
+    }
+
+    #[test]
+    pub fn uv_around_and_along_axis() {
+        let uv = cone().uv_at(&Point3::new(0.0, 0.5, 1.0));
+
+        assert_eq!(uv, Point2::new(0.25, 0.5));
+    }
+}
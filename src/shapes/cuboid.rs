@@ -0,0 +1,142 @@
+use crate::color::Color;
+use crate::geometry::{BoundingBox, Line3d};
+use crate::radiance::Radiance;
+use crate::shapes::{Material, Shape};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Axis-aligned box between two opposite corners.
+pub struct Cuboid {
+    color: Color,
+    bounds: BoundingBox,
+    material: Material,
+}
+
+impl Cuboid {
+    pub fn new(min: Point3<f64>, max: Point3<f64>, color: Color) -> Self {
+        Cuboid {
+            color,
+            bounds: BoundingBox::new(min.inf(&max), min.sup(&max)),
+            material: Material::default(),
+        }
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Axis along which the face nearest to a point faces, and whether that
+    /// face is on the side of `max`.
+    fn face_at(&self, position: &Point3<f64>) -> (usize, bool) {
+        let mut nearest = (f64::INFINITY, (0, false));
+        for axis in 0..3 {
+            let bounds = [(self.bounds.min()[axis], false), (self.bounds.max()[axis], true)];
+            for &(bound, positive) in &bounds {
+                let distance = (position[axis] - bound).abs();
+                if distance < nearest.0 {
+                    nearest = (distance, (axis, positive));
+                }
+            }
+        }
+        nearest.1
+    }
+}
+
+impl Shape for Cuboid {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        match self.bounds.intersect(ray, f64::NEG_INFINITY, f64::INFINITY) {
+            Some((near, far)) => vec![
+                (self as &dyn Shape, ray.at(near)),
+                (self as &dyn Shape, ray.at(far)),
+            ],
+            None => Vec::new(),
+        }
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.color)
+    }
+
+    /// Planar mapping of each face from the `min` corner, with one unit of
+    /// texture coordinates per unit length along the two following axes.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        let (axis, _) = self.face_at(position);
+        let offset = position - self.bounds.min();
+        Point2::new(offset[(axis + 1) % 3], offset[(axis + 2) % 3])
+    }
+
+    fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        let (axis, positive) = self.face_at(position);
+        let mut normal = Vector3::zeros();
+        normal[axis] = if positive { 1.0 } else { -1.0 };
+        Unit::new_unchecked(normal)
+    }
+
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64> {
+        let (axis, _) = self.face_at(position);
+        let mut tangent = Vector3::zeros();
+        tangent[(axis + 1) % 3] = 1.0;
+        tangent
+    }
+
+    /// Textures see each face as the XY plane, so that solid textures such
+    /// as the checker show a flat pattern on all of them.
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        let uv = self.uv_at(position);
+        Point3::new(uv.x, uv.y, 0.0)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cuboid() -> Cuboid {
+        Cuboid::new(Point3::new(1.0, 1.0, 0.0), Point3::new(-1.0, 3.0, 2.0), Color::gray(255))
+    }
+
+    #[test]
+    pub fn ray_enters_and_leaves() {
+        let ray = Line3d::new(Point3::new(-5.0, 2.0, 1.0), Vector3::x_axis());
+
+        let cuboid = cuboid();
+        let hits = cuboid.intersect(&ray);
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].1, Point3::new(-1.0, 2.0, 1.0));
+        assert_eq!(hits[1].1, Point3::new(1.0, 2.0, 1.0));
+    }
+
+    #[test]
+    pub fn ray_misses_beside() {
+        let ray = Line3d::new(Point3::new(-5.0, 4.0, 1.0), Vector3::x_axis());
+
+        assert!(cuboid().intersect(&ray).is_empty());
+    }
+
+    #[test]
+    pub fn normals_point_out_of_faces() {
+        let cuboid = cuboid();
+
+        assert_eq!(cuboid.normal_at(&Point3::new(-1.0, 2.0, 1.0)), -Vector3::x_axis());
+        assert_eq!(cuboid.normal_at(&Point3::new(0.5, 3.0, 1.5)), Vector3::y_axis());
+        assert_eq!(cuboid.normal_at(&Point3::new(0.0, 2.0, 0.0)), -Vector3::z_axis());
+    }
+
+    #[test]
+    pub fn uv_follows_face() {
+        let cuboid = cuboid();
+
+        assert_eq!(cuboid.uv_at(&Point3::new(0.5, 3.0, 1.5)), Point2::new(1.5, 1.5));
+        assert_eq!(cuboid.uv_at(&Point3::new(0.5, 1.5, 2.0)), Point2::new(1.5, 0.5));
+    }
+}
@@ -0,0 +1,214 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::geometry::{BoundingBox, Line3d};
+use crate::math::Polynomial;
+use crate::radiance::Radiance;
+use crate::shapes::{Material, Shape};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Cylinder standing on the horizontal plane through `base`, with its axis
+/// along Z. Finite cylinders are open tubes unless given end caps.
+pub struct Cylinder {
+    color: Color,
+    base: Point3<f64>,
+    radius: f64,
+    /// `None` for a cylinder extending infinitely in both directions.
+    height: Option<f64>,
+    capped: bool,
+    material: Material,
+}
+
+impl Cylinder {
+    /// Cylinder standing on `base`, which must have a positive height.
+    pub fn new(base: Point3<f64>, radius: f64, height: f64, color: Color) -> Self {
+        Cylinder {
+            color,
+            base,
+            radius,
+            height: Some(height),
+            capped: false,
+            material: Material::default(),
+        }
+    }
+
+    /// Cylinder of infinite length around the vertical axis through `point`.
+    pub fn infinite(point: Point3<f64>, radius: f64, color: Color) -> Self {
+        Cylinder {
+            height: None,
+            ..Cylinder::new(point, radius, 0.0, color)
+        }
+    }
+
+    /// Closes both ends of a finite cylinder with disks.
+    pub fn with_caps(mut self) -> Self {
+        self.capped = true;
+        self
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Height of the cap nearest to a point on the surface, if it lies on
+    /// one rather than on the side.
+    fn cap_at(&self, offset: &Vector3<f64>) -> Option<f64> {
+        let height = self.height.filter(|_| self.capped)?;
+        let cap = if offset.z < 0.5 * height { 0.0 } else { height };
+        let side_distance = (offset.x.hypot(offset.y) - self.radius).abs();
+        if (offset.z - cap).abs() < side_distance {
+            Some(cap)
+        } else {
+            None
+        }
+    }
+}
+
+impl Shape for Cylinder {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        let origin = ray.origin() - self.base;
+        let direction = ray.direction();
+        let a = direction.x * direction.x + direction.y * direction.y;
+        let b = 2.0 * (origin.x * direction.x + origin.y * direction.y);
+        let c = origin.x * origin.x + origin.y * origin.y - self.radius * self.radius;
+
+        let mut distances: Vec<f64> = Polynomial::new(vec![c, b, a])
+            .into_solutions()
+            .into_iter()
+            .filter(|d| match self.height {
+                Some(height) => (0.0..=height).contains(&(origin.z + d * direction.z)),
+                None => true,
+            })
+            .collect();
+        if let Some(height) = self.height.filter(|_| self.capped && direction.z != 0.0) {
+            for cap in [0.0, height].iter() {
+                let d = (cap - origin.z) / direction.z;
+                let hit = origin + direction * d;
+                if hit.x * hit.x + hit.y * hit.y <= self.radius * self.radius {
+                    distances.push(d);
+                }
+            }
+        }
+
+        distances
+            .into_iter()
+            .map(|d| (self as &dyn Shape, ray.at(d)))
+            .collect()
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.color)
+    }
+
+    /// Angle around the axis and the fraction of the height on the side (or
+    /// the height itself on infinite cylinders), and planar X and Y
+    /// coordinates on the caps.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        let offset = position - self.base;
+        if self.cap_at(&offset).is_some() {
+            return Point2::new(offset.x, offset.y);
+        }
+
+        let u = (offset.y.atan2(offset.x) / (2.0 * PI)).rem_euclid(1.0);
+        let v = match self.height {
+            Some(height) => offset.z / height,
+            None => offset.z,
+        };
+        Point2::new(u, v)
+    }
+
+    fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        let offset = position - self.base;
+        match self.cap_at(&offset) {
+            Some(cap) if cap > 0.0 => Vector3::z_axis(),
+            Some(_) => -Vector3::z_axis(),
+            None => Unit::new_normalize(Vector3::new(offset.x, offset.y, 0.0)),
+        }
+    }
+
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64> {
+        let offset = position - self.base;
+        match self.cap_at(&offset) {
+            Some(_) => Vector3::x(),
+            None => Vector3::new(-offset.y, offset.x, 0.0),
+        }
+    }
+
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        Point3::from(position - self.base)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let height = self.height?;
+        Some(BoundingBox::new(
+            self.base - Vector3::new(self.radius, self.radius, 0.0),
+            self.base + Vector3::new(self.radius, self.radius, height),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::test_utils::distances;
+
+    fn cylinder() -> Cylinder {
+        Cylinder::new(Point3::new(1.0, 1.0, 0.0), 1.0, 2.0, Color::gray(255))
+    }
+
+    #[test]
+    pub fn ray_crosses_side_twice() {
+        let ray = Line3d::new(Point3::new(-5.0, 1.0, 1.0), Vector3::x_axis());
+
+        assert_eq!(distances(&cylinder(), &ray), vec![5.0, 7.0]);
+        assert_eq!(cylinder().normal_at(&Point3::new(0.0, 1.0, 1.0)), -Vector3::x_axis());
+    }
+
+    #[test]
+    pub fn open_cylinder_is_hollow_along_axis() {
+        let ray = Line3d::new(Point3::new(1.0, 1.0, 5.0), -Vector3::z_axis());
+
+        assert!(cylinder().intersect(&ray).is_empty());
+    }
+
+    #[test]
+    pub fn caps_close_the_ends() {
+        let capped = cylinder().with_caps();
+        let ray = Line3d::new(Point3::new(1.5, 1.0, 5.0), -Vector3::z_axis());
+
+        assert_eq!(distances(&capped, &ray), vec![3.0, 5.0]);
+        assert_eq!(capped.normal_at(&Point3::new(1.5, 1.0, 2.0)), Vector3::z_axis());
+        assert_eq!(capped.normal_at(&Point3::new(1.5, 1.0, 0.0)), -Vector3::z_axis());
+        assert_eq!(capped.normal_at(&Point3::new(2.0, 1.0, 1.9)), Vector3::x_axis());
+    }
+
+    #[test]
+    pub fn finite_cylinder_ends_at_height() {
+        let ray = Line3d::new(Point3::new(-5.0, 1.0, 2.5), Vector3::x_axis());
+
+        assert!(cylinder().with_caps().intersect(&ray).is_empty());
+        assert!(cylinder().bounding_box().is_some());
+    }
+
+    #[test]
+    pub fn infinite_cylinder_has_no_ends() {
+        let infinite = Cylinder::infinite(Point3::new(1.0, 1.0, 0.0), 1.0, Color::gray(255));
+        let ray = Line3d::new(Point3::new(-5.0, 1.0, -100.0), Vector3::x_axis());
+
+        assert_eq!(distances(&infinite, &ray), vec![5.0, 7.0]);
+        assert!(infinite.bounding_box().is_none());
+    }
+
+    #[test]
+    pub fn uv_around_and_along_axis() {
+        let uv = cylinder().uv_at(&Point3::new(1.0, 2.0, 0.5));
+
+        assert_eq!(uv, Point2::new(0.25, 0.25));
+    }
+}
@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::geometry::{BoundingBox, Line3d, Plane3d};
+use crate::radiance::Radiance;
+use crate::sampling;
+use crate::shapes::{Material, Shape};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Flat disk facing along its normal.
+pub struct Disk {
+    color: Color,
+    plane: Plane3d,
+    radius: f64,
+    material: Material,
+}
+
+impl Disk {
+    pub fn new(center: Point3<f64>, normal: Unit<Vector3<f64>>, radius: f64, color: Color) -> Self {
+        Disk {
+            color,
+            plane: Plane3d::new(center, normal),
+            radius,
+            material: Material::default(),
+        }
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+}
+
+impl Shape for Disk {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        self.plane
+            .intersect(ray)
+            .map(|d| ray.at(d))
+            .filter(|position| (position - self.plane.origin()).norm() <= self.radius)
+            .map(|position| (self as &dyn Shape, position))
+            .into_iter()
+            .collect()
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.color)
+    }
+
+    /// Planar mapping relative to the center, with one unit of texture
+    /// coordinates per unit length.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        self.plane.coordinates(position)
+    }
+
+    fn normal_at(&self, _: &Point3<f64>) -> Unit<Vector3<f64>> {
+        self.plane.normal()
+    }
+
+    fn tangent_at(&self, _: &Point3<f64>) -> Vector3<f64> {
+        self.plane.tangent().into_inner()
+    }
+
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        let uv = self.plane.coordinates(position);
+        Point3::new(uv.x, uv.y, 0.0)
+    }
+
+    /// Samples the disk uniformly by area.
+    fn sample_surface(&self, from: &Point3<f64>, (u, v): (f64, f64)) -> Option<(Point3<f64>, f64)> {
+        let (radius, angle) = (self.radius * u.sqrt(), 2.0 * PI * v);
+        let position = self.plane.origin()
+            + self.plane.tangent().into_inner() * (radius * angle.cos())
+            + self.plane.bitangent().into_inner() * (radius * angle.sin());
+        let pdf = self.surface_pdf(from, &position);
+        if pdf > 0.0 {
+            Some((position, pdf))
+        } else {
+            None
+        }
+    }
+
+    fn surface_pdf(&self, from: &Point3<f64>, position: &Point3<f64>) -> f64 {
+        sampling::area_pdf(from, position, &self.plane.normal(), self.area())
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let normal = self.plane.normal();
+        let extent = Vector3::new(
+            (1.0 - normal.x * normal.x).max(0.0).sqrt(),
+            (1.0 - normal.y * normal.y).max(0.0).sqrt(),
+            (1.0 - normal.z * normal.z).max(0.0).sqrt(),
+        ) * self.radius;
+        let center = self.plane.origin();
+        Some(BoundingBox::new(center - extent, center + extent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk() -> Disk {
+        Disk::new(Point3::new(0.0, 0.0, 2.0), -Vector3::z_axis(), 1.0, Color::gray(255))
+    }
+
+    #[test]
+    pub fn ray_hits_within_radius() {
+        let inside = Line3d::new(Point3::new(0.5, 0.5, 0.0), Vector3::z_axis());
+        let outside = Line3d::new(Point3::new(1.0, 0.5, 0.0), Vector3::z_axis());
+
+        assert_eq!(disk().intersect(&inside)[0].1, Point3::new(0.5, 0.5, 2.0));
+        assert!(disk().intersect(&outside).is_empty());
+    }
+
+    #[test]
+    pub fn samples_lie_on_disk() {
+        let disk = disk();
+        let from = Point3::new(0.0, 0.0, 0.0);
+
+        for (i, j) in iproduct!(0..10, 0..10) {
+            let random = (i as f64 / 10.0, j as f64 / 10.0);
+            let (position, pdf) = disk.sample_surface(&from, random).unwrap();
+
+            assert!((position.z - 2.0).abs() < 1e-12);
+            assert!(position.coords.xy().norm() <= 1.0 + 1e-12);
+            assert_eq!(pdf, disk.surface_pdf(&from, &position));
+        }
+        // Directly below the center, the density is distance² / area.
+        let pdf = disk.surface_pdf(&from, &Point3::new(0.0, 0.0, 2.0));
+        assert!((pdf - 4.0 / PI).abs() < 1e-12);
+    }
+
+    #[test]
+    pub fn tilted_disk_bounds() {
+        let normal = Unit::new_normalize(Vector3::new(1.0, 0.0, 1.0));
+        let disk = Disk::new(Point3::origin(), normal, 1.0, Color::gray(255));
+
+        let bounds = disk.bounding_box().unwrap();
+
+        let half = 0.5f64.sqrt();
+        assert!((bounds.max() - Point3::new(half, 1.0, half)).norm() < 1e-12);
+    }
+}
@@ -1,8 +1,56 @@
+use std::sync::Arc;
+
+use crate::radiance::Radiance;
+use crate::shapes::MetallicRoughness;
+use crate::textures::{Bump, Texture};
+
+#[derive(Clone)]
 pub struct Material {
     pub ambient_light: f64,
     pub diffuse_coefficient: f64,
     pub specular_coefficient: f64,
     pub reflectiveness: f64,
+    /// Spread of reflections, from 0 for a perfect mirror to 1 for a matte
+    /// finish. Ignored in favor of the physically based roughness when `pbr`
+    /// is set.
+    pub roughness: f64,
     pub transparency: f64,
     pub refractive_index: f64,
-}
\ No newline at end of file
+    /// Base color replacing the shape's own color.
+    pub texture: Option<Arc<dyn Texture>>,
+    /// Detail perturbing the shading normal.
+    pub bump: Option<Bump>,
+    /// Physically based shading replacing the diffuse and specular
+    /// coefficients.
+    pub pbr: Option<MetallicRoughness>,
+    /// Light given off by the surface on both sides, independent of any
+    /// light falling on it.
+    pub emission: Radiance,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            ambient_light: 0.0,
+            diffuse_coefficient: 0.5,
+            specular_coefficient: 0.5,
+            reflectiveness: 0.0,
+            roughness: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            texture: None,
+            bump: None,
+            pbr: None,
+            emission: Radiance::black(),
+        }
+    }
+}
+
+impl Material {
+    /// Spread of the reflections added by `reflectiveness`, taken from the
+    /// physically based model if there is one so that a surface has a single
+    /// roughness.
+    pub fn reflection_roughness(&self) -> f64 {
+        self.pbr.map_or(self.roughness, |pbr| pbr.roughness)
+    }
+}
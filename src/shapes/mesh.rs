@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use crate::bvh::Bvh;
+use crate::color::Color;
+use crate::geometry::{BoundingBox, Line3d};
+use crate::radiance::Radiance;
+use crate::sampling::PiecewiseConstant;
+use crate::shapes::{Material, Shape, Triangle};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: usize,
+    pub tex_coord: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+pub struct MeshData {
+    pub positions: Vec<Point3<f64>>,
+    pub tex_coords: Vec<Point2<f64>>,
+    pub normals: Vec<Unit<Vector3<f64>>>,
+    pub color: Color,
+    pub material: Material,
+    /// Total surface area of the triangles.
+    pub area: f64,
+}
+
+pub struct Mesh {
+    data: Arc<MeshData>,
+    triangles: Vec<Triangle>,
+    bvh: Bvh,
+    /// Triangles picked in proportion to their area when sampling the mesh.
+    areas: PiecewiseConstant,
+}
+
+impl Mesh {
+    pub fn new(
+        positions: Vec<Point3<f64>>,
+        tex_coords: Vec<Point2<f64>>,
+        normals: Vec<Unit<Vector3<f64>>>,
+        faces: Vec<[Vertex; 3]>,
+        color: Color,
+        material: Material,
+    ) -> Self {
+        let areas: Vec<f64> = faces
+            .iter()
+            .map(|face| {
+                let [a, b, c] = face.map(|vertex| positions[vertex.position]);
+                (b - a).cross(&(c - a)).norm() / 2.0
+            })
+            .collect();
+        let data = Arc::new(MeshData {
+            positions,
+            tex_coords,
+            normals,
+            color,
+            material,
+            area: areas.iter().sum(),
+        });
+        let triangles: Vec<Triangle> = faces
+            .into_iter()
+            .map(|vertices| Triangle::new(data.clone(), vertices))
+            .collect();
+        let bounds: Vec<_> = triangles
+            .iter()
+            .map(|triangle| triangle.bounding_box())
+            .collect();
+
+        Mesh {
+            data,
+            triangles,
+            bvh: Bvh::new(&bounds),
+            areas: PiecewiseConstant::new(&areas),
+        }
+    }
+
+    fn triangle_at(&self, position: &Point3<f64>) -> Option<&Triangle> {
+        // Allows for rounding errors in positions on axis-aligned triangles,
+        // whose boxes are flat.
+        let margin = 1e-9 * (1.0 + position.coords.amax());
+        let mut nearest: Option<(&Triangle, f64)> = None;
+        self.bvh.containing(position, margin, |index| {
+            let triangle = &self.triangles[index];
+            if !triangle.contains_projection_of(position) {
+                return;
+            }
+            let distance = triangle.distance_to_plane(position);
+            let better = match nearest {
+                Some((_, best)) => distance < best,
+                None => true,
+            };
+            if better {
+                nearest = Some((triangle, distance));
+            }
+        });
+        nearest.map(|(triangle, _)| triangle)
+    }
+}
+
+impl Shape for Mesh {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        let mut intersections = Vec::new();
+        self.bvh
+            .any(ray, f64::NEG_INFINITY, f64::INFINITY, |index| {
+                intersections.extend(self.triangles[index].intersect(ray));
+                false
+            });
+        intersections
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        match self.triangle_at(position) {
+            Some(triangle) => triangle.color_at(position),
+            None => Radiance::from(&self.data.color),
+        }
+    }
+
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        self.triangle_at(position)
+            .map(|triangle| triangle.uv_at(position))
+            .unwrap_or_else(Point2::origin)
+    }
+
+    fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        // Intersections report the hit triangle itself, so this is only reached
+        // when the mesh is queried directly.
+        self.triangle_at(position)
+            .map(|triangle| triangle.normal_at(position))
+            .unwrap_or_else(Vector3::z_axis)
+    }
+
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64> {
+        self.triangle_at(position)
+            .map(|triangle| triangle.tangent_at(position))
+            .unwrap_or_else(Vector3::x)
+    }
+
+    fn sample_surface(&self, from: &Point3<f64>, (u, v): (f64, f64)) -> Option<(Point3<f64>, f64)> {
+        if self.triangles.is_empty() {
+            return None;
+        }
+
+        // The offset within the chosen bin is a fresh uniform number.
+        let (index, offset) = self.areas.sample(u);
+        let triangle = &self.triangles[index];
+        let position = triangle.point_at((offset, v));
+        let pdf = triangle.surface_pdf(from, &position);
+        if pdf > 0.0 {
+            Some((position, pdf))
+        } else {
+            None
+        }
+    }
+
+    fn surface_pdf(&self, from: &Point3<f64>, position: &Point3<f64>) -> f64 {
+        self.triangle_at(position)
+            .map(|triangle| triangle.surface_pdf(from, position))
+            .unwrap_or(0.0)
+    }
+
+    fn material(&self) -> &Material {
+        &self.data.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        self.bvh.bounds()
+    }
+}
@@ -0,0 +1,257 @@
+use std::f64::consts::PI;
+
+use crate::geometry::utils::*;
+use crate::radiance::Radiance;
+use crate::sampling;
+
+use nalgebra::{Unit, Vector3};
+use rand::Rng;
+
+/// Reflectance of dielectrics at normal incidence.
+const DIELECTRIC_REFLECTANCE: f64 = 0.04;
+/// Lower bound on the GGX roughness, as perfectly smooth microfacets would
+/// make the distribution a delta function.
+const MIN_ALPHA: f64 = 1e-3;
+
+/// Physically based surface following the glTF metallic-roughness model: a
+/// GGX microfacet specular lobe over a Lambertian base, both tinted by the
+/// shape's color and weighted by Schlick's Fresnel term so that the surface
+/// never reflects more light than it receives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MetallicRoughness {
+    /// Blend between a dielectric (0) and a metal (1).
+    pub metallic: f64,
+    /// Perceptual roughness, squared into the GGX `alpha`.
+    pub roughness: f64,
+}
+
+impl MetallicRoughness {
+    pub fn new(metallic: f64, roughness: f64) -> Self {
+        MetallicRoughness {
+            metallic: metallic.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+        }
+    }
+
+    fn alpha(&self) -> f64 {
+        (self.roughness * self.roughness).max(MIN_ALPHA)
+    }
+
+    /// Specular reflectance at an angle whose cosine is `cos`, tending to
+    /// white at grazing angles.
+    pub fn fresnel(&self, base: &Radiance, cos: f64) -> Radiance {
+        let f0 = Radiance::gray(DIELECTRIC_REFLECTANCE).mix(base, self.metallic);
+        f0.mix(&Radiance::gray(1.0), (1.0 - cos.clamp(0.0, 1.0)).powi(5))
+    }
+
+    /// GGX normal distribution function.
+    fn distribution(&self, cos_h: f64) -> f64 {
+        let alpha2 = self.alpha().powi(2);
+        let denominator = cos_h * cos_h * (alpha2 - 1.0) + 1.0;
+        alpha2 / (PI * denominator * denominator)
+    }
+
+    /// Height-correlated Smith masking-shadowing divided by `4 cos_l cos_v`.
+    fn visibility(&self, cos_l: f64, cos_v: f64) -> f64 {
+        let alpha2 = self.alpha().powi(2);
+        let ggx_v = cos_l * (cos_v * cos_v * (1.0 - alpha2) + alpha2).sqrt();
+        let ggx_l = cos_v * (cos_l * cos_l * (1.0 - alpha2) + alpha2).sqrt();
+        0.5 / (ggx_v + ggx_l)
+    }
+
+    /// BRDF for light arriving along `incoming` and leaving along `outgoing`,
+    /// both pointing away from the surface.
+    pub fn evaluate(
+        &self,
+        base: &Radiance,
+        normal: &Unit<Vector3<f64>>,
+        incoming: &Vector3<f64>,
+        outgoing: &Vector3<f64>,
+    ) -> Radiance {
+        let cos_l = incoming.dot(normal);
+        let cos_v = outgoing.dot(normal);
+        if cos_l <= 0.0 || cos_v <= 0.0 {
+            return Radiance::black();
+        }
+
+        let half = (incoming + outgoing).normalize();
+        let fresnel = self.fresnel(base, outgoing.dot(&half));
+        let cos_h = half.dot(normal);
+        let specular = fresnel * (self.distribution(cos_h) * self.visibility(cos_l, cos_v));
+        let diffuse = (Radiance::gray(1.0) - fresnel) * *base * ((1.0 - self.metallic) / PI);
+
+        diffuse + specular
+    }
+
+    /// Probability of sampling the specular rather than the diffuse lobe,
+    /// following their estimated contributions towards `outgoing`.
+    fn specular_probability(
+        &self,
+        base: &Radiance,
+        normal: &Unit<Vector3<f64>>,
+        outgoing: &Vector3<f64>,
+    ) -> f64 {
+        let specular = self.fresnel(base, outgoing.dot(normal)).luminance();
+        let diffuse = base.luminance() * (1.0 - self.metallic) * (1.0 - specular);
+        if specular + diffuse > 0.0 {
+            specular / (specular + diffuse)
+        } else {
+            1.0
+        }
+    }
+
+    /// Solid angle density with which `sample` picks `incoming`.
+    pub fn pdf(
+        &self,
+        base: &Radiance,
+        normal: &Unit<Vector3<f64>>,
+        incoming: &Vector3<f64>,
+        outgoing: &Vector3<f64>,
+    ) -> f64 {
+        let cos_l = incoming.dot(normal);
+        if cos_l <= 0.0 || outgoing.dot(normal) <= 0.0 {
+            return 0.0;
+        }
+
+        let half = (incoming + outgoing).normalize();
+        let cos_h = half.dot(normal);
+        let specular = self.distribution(cos_h) * cos_h / (4.0 * outgoing.dot(&half));
+        let diffuse = cos_l / PI;
+        let probability = self.specular_probability(base, normal, outgoing);
+
+        probability * specular + (1.0 - probability) * diffuse
+    }
+
+    /// Samples a direction of incoming light, choosing between the specular
+    /// lobe (through a GGX microfacet normal) and the diffuse lobe. Returns
+    /// `None` if the sample falls below the surface.
+    pub fn sample<R: Rng>(
+        &self,
+        base: &Radiance,
+        normal: &Unit<Vector3<f64>>,
+        outgoing: &Vector3<f64>,
+        rng: &mut R,
+    ) -> Option<Vector3<f64>> {
+        let direction = if rng.gen::<f64>() < self.specular_probability(base, normal, outgoing) {
+            let half = sampling::ggx(normal, self.alpha(), rng);
+            (-outgoing).reflect(&half)
+        } else {
+            sampling::cosine_hemisphere(normal, rng).into_inner()
+        };
+
+        if direction.dot(normal) > 0.0 {
+            Some(direction)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const SAMPLES: usize = 50_000;
+
+    fn outgoing() -> Vector3<f64> {
+        Vector3::new(0.6, 0.0, 0.8)
+    }
+
+    /// Uniformly distributed directions over the upper hemisphere.
+    fn uniform_directions(rng: &mut StdRng) -> impl Iterator<Item = Vector3<f64>> + '_ {
+        (0..SAMPLES).map(move |_| {
+            sampling::around(&Vector3::z_axis(), rng.gen::<f64>(), 2.0 * PI * rng.gen::<f64>())
+                .into_inner()
+        })
+    }
+
+    #[test]
+    pub fn fresnel_at_normal_incidence() {
+        let base = Radiance::new(0.9, 0.6, 0.2);
+
+        assert_eq!(MetallicRoughness::new(0.0, 0.5).fresnel(&base, 1.0), Radiance::gray(0.04));
+        assert_eq!(MetallicRoughness::new(1.0, 0.5).fresnel(&base, 1.0), base);
+        assert_eq!(MetallicRoughness::new(0.0, 0.5).fresnel(&base, 0.0), Radiance::gray(1.0));
+    }
+
+    #[test]
+    pub fn pdf_integrates_to_accepted_samples() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let base = Radiance::gray(0.5);
+        let normal = Vector3::z_axis();
+
+        for &(metallic, roughness) in &[(0.0, 0.3), (0.5, 0.6), (1.0, 0.8)] {
+            let material = MetallicRoughness::new(metallic, roughness);
+            let integral: f64 = uniform_directions(&mut rng)
+                .map(|incoming| material.pdf(&base, &normal, &incoming, &outgoing()))
+                .sum::<f64>()
+                * 2.0
+                * PI
+                / SAMPLES as f64;
+            // Microfacet samples mirrored below the surface are rejected.
+            let accepted = (0..SAMPLES)
+                .filter_map(|_| material.sample(&base, &normal, &outgoing(), &mut rng))
+                .count() as f64
+                / SAMPLES as f64;
+
+            assert!(integral <= 1.01, "{} {} {}", metallic, roughness, integral);
+            assert!((integral - accepted).abs() < 0.02, "{} {} {}", metallic, integral, accepted);
+        }
+    }
+
+    #[test]
+    pub fn sampling_follows_pdf() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let base = Radiance::new(0.8, 0.5, 0.3);
+        let material = MetallicRoughness::new(0.3, 0.5);
+        let normal = Vector3::z_axis();
+
+        // Estimate the directional albedo both by importance sampling and
+        // uniformly over the hemisphere.
+        let importance: f64 = (0..SAMPLES)
+            .filter_map(|_| material.sample(&base, &normal, &outgoing(), &mut rng))
+            .map(|incoming| {
+                let pdf = material.pdf(&base, &normal, &incoming, &outgoing());
+                let brdf = material.evaluate(&base, &normal, &incoming, &outgoing());
+                brdf.luminance() * incoming.z / pdf
+            })
+            .sum::<f64>()
+            / SAMPLES as f64;
+        let uniform: f64 = uniform_directions(&mut rng)
+            .map(|incoming| {
+                material.evaluate(&base, &normal, &incoming, &outgoing()).luminance() * incoming.z
+            })
+            .sum::<f64>()
+            * 2.0
+            * PI
+            / SAMPLES as f64;
+
+        assert!((importance - uniform).abs() < 0.01, "{} {}", importance, uniform);
+    }
+
+    #[test]
+    pub fn reflects_no_more_than_received() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let white = Radiance::gray(1.0);
+
+        for &(metallic, roughness) in &[(0.0, 0.1), (0.0, 1.0), (1.0, 0.2), (1.0, 1.0)] {
+            let material = MetallicRoughness::new(metallic, roughness);
+            let albedo = (0..SAMPLES)
+                .filter_map(|_| material.sample(&white, &Vector3::z_axis(), &outgoing(), &mut rng))
+                .map(|incoming| {
+                    let pdf = material.pdf(&white, &Vector3::z_axis(), &incoming, &outgoing());
+                    let brdf =
+                        material.evaluate(&white, &Vector3::z_axis(), &incoming, &outgoing());
+                    brdf.luminance() * incoming.z / pdf
+                })
+                .sum::<f64>()
+                / SAMPLES as f64;
+
+            // Single scattering GGX loses energy at high roughness.
+            assert!(albedo < 1.01, "{} {} {}", metallic, roughness, albedo);
+            assert!(albedo > 0.3, "{} {} {}", metallic, roughness, albedo);
+        }
+    }
+}
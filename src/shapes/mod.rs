@@ -1,9 +1,32 @@
-pub use self::checkerboard::CheckerBoard;
+pub use self::cone::Cone;
+pub use self::cuboid::Cuboid;
+pub use self::cylinder::Cylinder;
+pub use self::disk::Disk;
+pub use self::mesh::{Mesh, MeshData, Vertex};
 pub use self::shape::Shape;
 pub use self::sphere::Sphere;
+pub use self::torus::Torus;
+pub use self::transformed::{Instance, Transformed};
 pub use self::material::Material;
+pub use self::microfacet::MetallicRoughness;
+pub use self::plane::Plane;
+pub use self::rectangle::Rectangle;
+pub use self::triangle::Triangle;
 
-mod checkerboard;
+mod cone;
+mod cuboid;
+mod cylinder;
+mod disk;
+mod mesh;
 mod shape;
 mod sphere;
-mod material;
\ No newline at end of file
+mod torus;
+mod transformed;
+mod material;
+mod microfacet;
+mod plane;
+mod rectangle;
+mod triangle;
+
+#[cfg(test)]
+mod test_utils;
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::geometry::{BoundingBox, Line3d, Plane3d};
+use crate::radiance::Radiance;
+use crate::shapes::{Material, Shape};
+use crate::textures::Checker;
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Infinite plane through a point, facing along its normal.
+pub struct Plane {
+    color: Color,
+    plane: Plane3d,
+    material: Material,
+}
+
+impl Plane {
+    pub fn new(point: Point3<f64>, normal: Unit<Vector3<f64>>, color: Color) -> Self {
+        Plane {
+            color,
+            plane: Plane3d::new(point, normal),
+            material: Material::default(),
+        }
+    }
+
+    /// Reflective checkered floor at the given height.
+    pub fn checkerboard(height: f64) -> Self {
+        Plane::new(Point3::new(0.0, 0.0, height), Vector3::z_axis(), Color::gray(128)).with_material(
+            Material {
+                ambient_light: 0.0,
+                diffuse_coefficient: 0.6,
+                specular_coefficient: 0.4,
+                reflectiveness: 0.8,
+                roughness: 0.0,
+                transparency: 0.0,
+                refractive_index: 1.0,
+                texture: Some(Arc::new(Checker::board())),
+                bump: None,
+                pbr: None,
+                emission: Radiance::black(),
+            },
+        )
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+}
+
+impl Shape for Plane {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        self.plane
+            .intersect(ray)
+            .map(|d| (self as &dyn Shape, ray.at(d)))
+            .into_iter()
+            .collect()
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.color)
+    }
+
+    /// Planar mapping with one unit of texture coordinates per unit length,
+    /// upright on vertical planes and along X and Y on horizontal ones.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        self.plane.coordinates(position)
+    }
+
+    fn normal_at(&self, _: &Point3<f64>) -> Unit<Vector3<f64>> {
+        self.plane.normal()
+    }
+
+    fn tangent_at(&self, _: &Point3<f64>) -> Vector3<f64> {
+        self.plane.tangent().into_inner()
+    }
+
+    /// Textures see the plane as the XY plane, so that solid textures such
+    /// as the checker show a flat pattern whatever its orientation.
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        let uv = self.plane.coordinates(position);
+        Point3::new(uv.x, uv.y, 0.0)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn tilted_plane_faces_its_normal() {
+        let normal = Unit::new_normalize(Vector3::new(1.0, 0.0, 1.0));
+        let plane = Plane::new(Point3::new(1.0, 0.0, 0.0), normal, Color::gray(255));
+        let ray = Line3d::new(Point3::new(1.0, 0.0, 2.0), -Vector3::z_axis());
+
+        let hits = plane.intersect(&ray);
+
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].1 - Point3::new(1.0, 0.0, 0.0)).norm() < 1e-12);
+        assert_eq!(plane.normal_at(&hits[0].1), normal);
+    }
+
+    #[test]
+    pub fn checker_is_flat_on_walls() {
+        let wall = Plane::new(Point3::new(3.0, 0.0, 0.0), -Vector3::x_axis(), Color::gray(255))
+            .with_material(Material {
+                texture: Some(Arc::new(Checker::board())),
+                ..Material::default()
+            });
+
+        let lower = wall.color_at(&Point3::new(3.0, 0.5, 0.5));
+        let upper = wall.color_at(&Point3::new(3.0, 0.5, 1.5));
+        let beside = wall.color_at(&Point3::new(3.0, -0.5, 0.5));
+
+        assert_eq!(wall.local_position(&Point3::new(3.0, 0.5, 0.5)).z, 0.0);
+        assert_ne!(lower, upper);
+        assert_ne!(lower, beside);
+        assert_eq!(upper, beside);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::color::Color;
+use crate::geometry::{BoundingBox, Line3d, Plane3d};
+use crate::radiance::Radiance;
+use crate::sampling;
+use crate::shapes::{Material, Shape};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Parallelogram spanned by two edges from a corner, facing along `u × v`.
+pub struct Rectangle {
+    color: Color,
+    corner: Point3<f64>,
+    u: Vector3<f64>,
+    v: Vector3<f64>,
+    plane: Plane3d,
+    material: Material,
+}
+
+impl Rectangle {
+    pub fn new(corner: Point3<f64>, u: Vector3<f64>, v: Vector3<f64>, color: Color) -> Self {
+        Rectangle {
+            color,
+            corner,
+            u,
+            v,
+            plane: Plane3d::new(corner, Unit::new_normalize(u.cross(&v))),
+            material: Material::default(),
+        }
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    fn area(&self) -> f64 {
+        self.u.cross(&self.v).norm()
+    }
+
+    /// Fractions of the edges `u` and `v` leading from the corner to a point
+    /// in the plane.
+    fn fractions(&self, position: &Point3<f64>) -> Point2<f64> {
+        let offset = position - self.corner;
+        let normal = self.u.cross(&self.v);
+        let area2 = normal.norm_squared();
+        Point2::new(
+            offset.cross(&self.v).dot(&normal) / area2,
+            self.u.cross(&offset).dot(&normal) / area2,
+        )
+    }
+}
+
+impl Shape for Rectangle {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        self.plane
+            .intersect(ray)
+            .map(|d| ray.at(d))
+            .filter(|position| {
+                let uv = self.fractions(position);
+                (0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y)
+            })
+            .map(|position| (self as &dyn Shape, position))
+            .into_iter()
+            .collect()
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.color)
+    }
+
+    /// Fractions of the edges from the corner, so that an image covers the
+    /// rectangle exactly once.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        self.fractions(position)
+    }
+
+    fn normal_at(&self, _: &Point3<f64>) -> Unit<Vector3<f64>> {
+        self.plane.normal()
+    }
+
+    fn tangent_at(&self, _: &Point3<f64>) -> Vector3<f64> {
+        self.u
+    }
+
+    /// Lengths along the edges from the corner, in the XY plane.
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        let uv = self.fractions(position);
+        Point3::new(uv.x * self.u.norm(), uv.y * self.v.norm(), 0.0)
+    }
+
+    /// Samples the rectangle uniformly by area.
+    fn sample_surface(&self, from: &Point3<f64>, (u, v): (f64, f64)) -> Option<(Point3<f64>, f64)> {
+        let position = self.corner + self.u * u + self.v * v;
+        let pdf = self.surface_pdf(from, &position);
+        if pdf > 0.0 {
+            Some((position, pdf))
+        } else {
+            None
+        }
+    }
+
+    fn surface_pdf(&self, from: &Point3<f64>, position: &Point3<f64>) -> f64 {
+        sampling::area_pdf(from, position, &self.plane.normal(), self.area())
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        Some(BoundingBox::around(&[
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle() -> Rectangle {
+        Rectangle::new(
+            Point3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Color::gray(255),
+        )
+    }
+
+    #[test]
+    pub fn ray_hits_within_edges() {
+        let ray = |y, z| Line3d::new(Point3::new(-1.0, y, z), Vector3::x_axis());
+
+        assert_eq!(rectangle().intersect(&ray(1.5, 0.5))[0].1, Point3::new(1.0, 1.5, 0.5));
+        assert!(rectangle().intersect(&ray(2.5, 0.5)).is_empty());
+        assert!(rectangle().intersect(&ray(1.5, -0.5)).is_empty());
+    }
+
+    #[test]
+    pub fn faces_along_cross_product_of_edges() {
+        assert_eq!(rectangle().normal_at(&Point3::new(1.0, 1.0, 0.5)), Vector3::x_axis());
+    }
+
+    #[test]
+    pub fn uv_spans_edges() {
+        let uv = rectangle().uv_at(&Point3::new(1.0, 1.5, 0.25));
+        let local = rectangle().local_position(&Point3::new(1.0, 1.5, 0.25));
+
+        assert_eq!(uv, Point2::new(0.75, 0.25));
+        assert_eq!(local, Point3::new(1.5, 0.25, 0.0));
+    }
+
+    #[test]
+    pub fn samples_cover_rectangle() {
+        let rectangle = rectangle();
+        let from = Point3::new(-1.0, 1.0, 0.5);
+
+        let (position, pdf) = rectangle.sample_surface(&from, (0.5, 0.5)).unwrap();
+
+        assert_eq!(position, Point3::new(1.0, 1.0, 0.5));
+        assert!((pdf - 4.0 / 2.0).abs() < 1e-12);
+        assert!(rectangle.sample_surface(&Point3::new(1.0, 5.0, 0.5), (0.5, 0.5)).is_none());
+    }
+}
@@ -1,29 +1,177 @@
+use std::sync::Arc;
+
 use crate::color::Color;
-use crate::geometry::{utils::*, Line3d};
+use crate::geometry::{utils::*, BoundingBox, Line3d};
+use crate::radiance::Radiance;
 use crate::shapes::Material;
 
-use nalgebra::{Point3, Unit, Vector3};
+use nalgebra::{Point2, Point3, Unit, Vector3};
 
 pub trait Shape: Send + Sync {
     fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)>;
-    fn color_at(&self, position: &Point3<f64>) -> Color;
+    fn color_at(&self, position: &Point3<f64>) -> Radiance;
+    /// Texture coordinates of a point on the surface.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64>;
     fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>>;
+    /// Direction of increasing `u` along the surface.
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64>;
     fn material(&self) -> &Material;
 
-    fn occludes(&self, a: &Point3<f64>, b: &Point3<f64>, radius: f64) -> bool {
-        self.intersect(&Line3d::between(a, b))
+    /// Position relative to the shape, at which procedural textures are
+    /// evaluated.
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        *position
+    }
+
+    /// Picks a point on the surface visible from `from`, for sampling the
+    /// light the shape emits, given two uniform random numbers. Returns the
+    /// point and the solid angle density of picking it, or `None` if the
+    /// shape cannot be sampled.
+    fn sample_surface(
+        &self,
+        _from: &Point3<f64>,
+        _random: (f64, f64),
+    ) -> Option<(Point3<f64>, f64)> {
+        None
+    }
+
+    /// Solid angle density with which `sample_surface` picks `position` as
+    /// seen from `from`.
+    fn surface_pdf(&self, _from: &Point3<f64>, _position: &Point3<f64>) -> f64 {
+        0.0
+    }
+
+    /// Color of the material's texture at a point, or the shape's own color
+    /// if the material has no texture.
+    fn textured_color(&self, position: &Point3<f64>, color: &Color) -> Radiance {
+        match &self.material().texture {
+            Some(texture) => {
+                texture.color_at(&self.uv_at(position), &self.local_position(position))
+            }
+            None => Radiance::from(color),
+        }
+    }
+
+    /// Normal used for shading, perturbed by the material's bump map.
+    fn shading_normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        let normal = self.normal_at(position);
+        match &self.material().bump {
+            Some(bump) => bump.perturb(
+                &normal,
+                &self.tangent_at(position),
+                &self.uv_at(position),
+                &self.local_position(position),
+            ),
+            None => normal,
+        }
+    }
+
+    /// Axis-aligned box containing the whole shape, or `None` for shapes of
+    /// infinite extent.
+    fn bounding_box(&self) -> Option<BoundingBox>;
+
+    /// Whether the shape blocks the ray between its origin and `distance`,
+    /// ignoring hits within `radius` of either end.
+    fn occludes(&self, ray: &Line3d, distance: f64, radius: f64) -> bool {
+        self.intersect(ray)
             .into_iter()
-            .filter(|&(_, position)| a.distance_to(&position) > radius)
-            .filter(|&(_, position)| b.distance_to(&position) > radius)
-            .filter(|&(_, position)| (position - a).dot(&(position - b)) < 0.0)
-            .count()
-            > 0
+            .map(|(_, position)| ray.project(position))
+            .any(|t| t > radius && t < distance - radius)
+    }
+
+    /// Mirrors the ray about the shading normal, or about the geometric
+    /// normal if a strongly bumped normal would send it into the surface.
+    fn reflect(&self, ray: &Line3d, point: &Point3<f64>) -> Line3d {
+        let normal = self.normal_at(point);
+        let bumped = ray.direction().reflect(&self.shading_normal_at(point));
+        let direction = if bumped.dot(&normal) * ray.direction().dot(&normal) < 0.0 {
+            bumped
+        } else {
+            ray.direction().reflect(&normal)
+        };
+        Line3d::new(*point, Unit::new_normalize(direction))
+    }
+
+    /// Whether the ray crosses the surface at this point from the outside in,
+    /// i.e. enters the medium enclosed by the shape.
+    fn entering(&self, ray: &Line3d, point: &Point3<f64>) -> bool {
+        ray.direction().dot(&self.normal_at(point)) < 0.0
+    }
+
+    /// Ray bent by passing through the surface, where `eta` is the ratio of
+    /// refractive indices on the incoming and outgoing side. Returns `None`
+    /// on total internal reflection.
+    fn refract(&self, ray: &Line3d, point: &Point3<f64>, eta: f64) -> Option<Line3d> {
+        ray.direction()
+            .refract(&self.normal_at(point), eta)
+            .map(|direction| Line3d::new(*point, Unit::new_normalize(direction)))
+    }
+}
+
+/// Shared shapes, such as the geometry behind many instances, behave like the
+/// shape itself.
+impl<S: Shape + ?Sized> Shape for Arc<S> {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        (**self).intersect(ray)
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        (**self).color_at(position)
+    }
+
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        (**self).uv_at(position)
+    }
+
+    fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        (**self).normal_at(position)
+    }
+
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64> {
+        (**self).tangent_at(position)
+    }
+
+    fn material(&self) -> &Material {
+        (**self).material()
+    }
+
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        (**self).local_position(position)
+    }
+
+    fn sample_surface(&self, from: &Point3<f64>, random: (f64, f64)) -> Option<(Point3<f64>, f64)> {
+        (**self).sample_surface(from, random)
+    }
+
+    fn surface_pdf(&self, from: &Point3<f64>, position: &Point3<f64>) -> f64 {
+        (**self).surface_pdf(from, position)
+    }
+
+    fn textured_color(&self, position: &Point3<f64>, color: &Color) -> Radiance {
+        (**self).textured_color(position, color)
+    }
+
+    fn shading_normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        (**self).shading_normal_at(position)
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        (**self).bounding_box()
+    }
+
+    fn occludes(&self, ray: &Line3d, distance: f64, radius: f64) -> bool {
+        (**self).occludes(ray, distance, radius)
     }
 
     fn reflect(&self, ray: &Line3d, point: &Point3<f64>) -> Line3d {
-        Line3d::new(
-            point.clone(),
-            Unit::new_normalize(ray.direction().reflect(&self.normal_at(point))),
-        )
+        (**self).reflect(ray, point)
+    }
+
+    fn entering(&self, ray: &Line3d, point: &Point3<f64>) -> bool {
+        (**self).entering(ray, point)
+    }
+
+    fn refract(&self, ray: &Line3d, point: &Point3<f64>, eta: f64) -> Option<Line3d> {
+        (**self).refract(ray, point, eta)
     }
 }
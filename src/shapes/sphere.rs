@@ -1,9 +1,13 @@
+use std::f64::consts::PI;
+
 use crate::color::Color;
-use crate::geometry::{utils::*, Line3d};
+use crate::geometry::{utils::*, BoundingBox, Line3d};
 use crate::math::Polynomial;
+use crate::radiance::Radiance;
+use crate::sampling;
 use crate::shapes::{Material, Shape};
 
-use nalgebra::{Point3, Unit, Vector3};
+use nalgebra::{Point2, Point3, Unit, Vector3};
 
 pub struct Sphere {
     color: Color,
@@ -18,20 +22,37 @@ impl Sphere {
             color,
             center,
             radius,
-            material: Material {
-                ambient_light: 0.0,
-                diffuse_coefficient: 0.5,
-                specular_coefficient: 0.5,
-                reflectiveness: 0.0,
-                transparency: 0.0,
-                refractive_index: 1.0,
-            },
+            material: Material::default(),
         }
     }
 
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn center(&self) -> &Point3<f64> {
+        &self.center
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
     pub fn collides_with(&self, other: &Self) -> bool {
         (self.center - other.center).norm() <= self.radius + other.radius
     }
+
+    /// Cosine of the half-angle of the cone the sphere subtends as seen
+    /// from a point outside of it.
+    fn cos_subtended(&self, from: &Point3<f64>) -> Option<f64> {
+        let distance2 = (self.center - from).norm_squared();
+        let radius2 = self.radius * self.radius;
+        if distance2 <= radius2 * (1.0 + 1e-6) {
+            return None;
+        }
+        Some((1.0 - radius2 / distance2).sqrt())
+    }
 }
 
 impl Shape for Sphere {
@@ -47,15 +68,93 @@ impl Shape for Sphere {
             .collect()
     }
 
-    fn color_at(&self, _: &Point3<f64>) -> Color {
-        self.color.clone()
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.color)
+    }
+
+    /// Longitude around the Z axis and latitude from the south pole.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        let direction = (position - self.center).normalize();
+        let u = (direction.y.atan2(direction.x) / (2.0 * PI)).rem_euclid(1.0);
+        let v = 1.0 - direction.z.clamp(-1.0, 1.0).acos() / PI;
+        Point2::new(u, v)
     }
 
     fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
         Unit::new_normalize(position - self.center)
     }
 
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64> {
+        let offset = position - self.center;
+        Vector3::new(-offset.y, offset.x, 0.0)
+    }
+
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        Point3::from(position - self.center)
+    }
+
+    /// Samples the cone of directions subtended by the sphere uniformly.
+    fn sample_surface(&self, from: &Point3<f64>, (u, v): (f64, f64)) -> Option<(Point3<f64>, f64)> {
+        let cos_max = self.cos_subtended(from)?;
+        let axis = from.unit_to(&self.center);
+        let cos_theta = 1.0 - u * (1.0 - cos_max);
+        let direction = sampling::around(&axis, cos_theta, 2.0 * PI * v);
+
+        // Nearest intersection of the sampled direction with the sphere.
+        let distance = (self.center - from).norm();
+        let sin2_theta = 1.0 - cos_theta * cos_theta;
+        let half_chord = (self.radius.powi(2) - distance * distance * sin2_theta).max(0.0).sqrt();
+        let position = from + direction.into_inner() * (distance * cos_theta - half_chord);
+
+        Some((position, 1.0 / (2.0 * PI * (1.0 - cos_max))))
+    }
+
+    fn surface_pdf(&self, from: &Point3<f64>, _: &Point3<f64>) -> f64 {
+        match self.cos_subtended(from) {
+            Some(cos_max) => 1.0 / (2.0 * PI * (1.0 - cos_max)),
+            None => 0.0,
+        }
+    }
+
     fn material(&self) -> &Material {
         &self.material
     }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let radius = Vector3::new(self.radius, self.radius, self.radius);
+        Some(BoundingBox::new(self.center - radius, self.center + radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn samples_visible_side_of_sphere() {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 3.0), 1.0, Color::gray(255));
+        let from = Point3::origin();
+
+        for (i, j) in iproduct!(0..10, 0..10) {
+            let random = (i as f64 / 10.0, j as f64 / 10.0);
+            let (position, pdf) = sphere.sample_surface(&from, random).unwrap();
+
+            assert!(((position - sphere.center).norm() - 1.0).abs() < 1e-9);
+            assert!(sphere.normal_at(&position).dot(&(from - position)) >= -1e-9);
+            assert_eq!(pdf, sphere.surface_pdf(&from, &position));
+        }
+        // Solid angle of the cone subtended by the sphere.
+        let (_, pdf) = sphere.sample_surface(&from, (0.5, 0.5)).unwrap();
+        let cos_max = (1.0 - 1.0 / 9.0f64).sqrt();
+        assert!((1.0 / pdf - 2.0 * PI * (1.0 - cos_max)).abs() < 1e-12);
+    }
+
+    #[test]
+    pub fn cannot_sample_from_inside() {
+        let sphere = Sphere::new(Point3::origin(), 1.0, Color::gray(255));
+
+        let inside = Point3::new(0.0, 0.5, 0.0);
+        assert!(sphere.sample_surface(&inside, (0.5, 0.5)).is_none());
+        assert_eq!(sphere.surface_pdf(&inside, &Point3::new(0.0, 1.0, 0.0)), 0.0);
+    }
 }
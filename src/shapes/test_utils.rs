@@ -0,0 +1,23 @@
+use crate::geometry::Line3d;
+use crate::shapes::Shape;
+
+/// Distances along the ray of all its intersections with the shape, nearest
+/// first.
+pub fn distances(shape: &dyn Shape, ray: &Line3d) -> Vec<f64> {
+    let mut distances: Vec<_> = shape
+        .intersect(ray)
+        .into_iter()
+        .map(|(_, position)| ray.project(position))
+        .collect();
+    distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    distances
+}
+
+/// Checks distances found by solving polynomials, which are only accurate to
+/// within rounding errors.
+pub fn assert_distances(actual: Vec<f64>, expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-6, "{:?} {:?}", actual, expected);
+    }
+}
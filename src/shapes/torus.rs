@@ -0,0 +1,177 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::geometry::{BoundingBox, Line3d};
+use crate::math::Polynomial;
+use crate::radiance::Radiance;
+use crate::shapes::{Material, Shape};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Ring around the Z axis, swept by a circle of radius `minor_radius` whose
+/// center stays at `major_radius` from the torus' center.
+pub struct Torus {
+    color: Color,
+    center: Point3<f64>,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Material,
+}
+
+impl Torus {
+    /// The minor radius must be positive for the ring to have a tube.
+    pub fn new(center: Point3<f64>, major_radius: f64, minor_radius: f64, color: Color) -> Self {
+        Torus {
+            color,
+            center,
+            major_radius,
+            minor_radius,
+            material: Material::default(),
+        }
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn center(&self) -> &Point3<f64> {
+        &self.center
+    }
+
+    /// Center of the tube's cross-section nearest to a point, relative to
+    /// the torus' center.
+    fn ring_point(&self, offset: &Vector3<f64>) -> Vector3<f64> {
+        let radial = Vector3::new(offset.x, offset.y, 0.0);
+        match radial.try_normalize(1e-12) {
+            Some(radial) => radial * self.major_radius,
+            None => Vector3::new(self.major_radius, 0.0, 0.0),
+        }
+    }
+}
+
+impl Shape for Torus {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        // Solving from the point of the ray closest to the center keeps the
+        // coefficients small for distant origins, and cancels the cubic term.
+        let direction = ray.direction();
+        let closest = -direction.dot(&(ray.origin() - self.center));
+        let origin = ray.origin() + direction * closest - self.center;
+
+        let major2 = self.major_radius * self.major_radius;
+        let k = origin.norm_squared() + major2 - self.minor_radius * self.minor_radius;
+        let radial_origin = origin.x * origin.x + origin.y * origin.y;
+        let radial_slope = origin.x * direction.x + origin.y * direction.y;
+        let radial_direction = direction.x * direction.x + direction.y * direction.y;
+
+        Polynomial::new(vec![
+            k * k - 4.0 * major2 * radial_origin,
+            -8.0 * major2 * radial_slope,
+            2.0 * k - 4.0 * major2 * radial_direction,
+            0.0,
+            1.0,
+        ])
+        .into_solutions()
+        .into_iter()
+        .map(|d| (self as &dyn Shape, ray.at(closest + d)))
+        .collect()
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.color)
+    }
+
+    /// Angle around the Z axis and around the tube, starting from its outer
+    /// equator and going over the top.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        let offset = position - self.center;
+        let u = (offset.y.atan2(offset.x) / (2.0 * PI)).rem_euclid(1.0);
+        let radial = offset.x.hypot(offset.y) - self.major_radius;
+        let v = (offset.z.atan2(radial) / (2.0 * PI)).rem_euclid(1.0);
+        Point2::new(u, v)
+    }
+
+    fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        let offset = position - self.center;
+        Unit::new_normalize(offset - self.ring_point(&offset))
+    }
+
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64> {
+        let offset = position - self.center;
+        Vector3::new(-offset.y, offset.x, 0.0)
+    }
+
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        Point3::from(position - self.center)
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vector3::new(outer, outer, self.minor_radius);
+        Some(BoundingBox::new(self.center - extent, self.center + extent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::test_utils::{assert_distances, distances};
+
+    fn torus() -> Torus {
+        Torus::new(Point3::new(1.0, 2.0, 3.0), 2.0, 0.5, Color::gray(255))
+    }
+
+    #[test]
+    pub fn ray_through_center_crosses_tube_twice() {
+        let torus = torus();
+        let ray = Line3d::new(Point3::new(-9.0, 2.0, 3.0), Vector3::x_axis());
+
+        assert_distances(distances(&torus, &ray), &[7.5, 8.5, 11.5, 12.5]);
+    }
+
+    #[test]
+    pub fn ray_along_axis_misses() {
+        let torus = torus();
+        let ray = Line3d::new(Point3::new(1.0, 2.0, 10.0), -Vector3::z_axis());
+
+        assert!(torus.intersect(&ray).is_empty());
+    }
+
+    #[test]
+    pub fn ray_grazing_tube_from_afar() {
+        let torus = torus();
+        let ray = Line3d::new(Point3::new(3.0, 2.0, 1003.0), -Vector3::z_axis());
+
+        assert_distances(distances(&torus, &ray), &[999.5, 1000.5]);
+    }
+
+    #[test]
+    pub fn normals_point_away_from_tube() {
+        let torus = torus();
+
+        let outer = torus.normal_at(&Point3::new(3.5, 2.0, 3.0));
+        let inner = torus.normal_at(&Point3::new(1.0, 0.5, 3.0));
+        let top = torus.normal_at(&Point3::new(1.0, 4.0, 3.5));
+
+        assert!((outer.into_inner() - Vector3::x()).norm() < 1e-12);
+        assert!((inner.into_inner() - Vector3::y()).norm() < 1e-12);
+        assert!((top.into_inner() - Vector3::z()).norm() < 1e-12);
+    }
+
+    #[test]
+    pub fn uv_around_axis_and_tube() {
+        let torus = torus();
+
+        let outer = torus.uv_at(&Point3::new(3.5, 2.0, 3.0));
+        let top = torus.uv_at(&Point3::new(1.0, 4.0, 3.5));
+        let inner = torus.uv_at(&Point3::new(-0.5, 2.0, 3.0));
+
+        assert_eq!(outer, Point2::new(0.0, 0.0));
+        assert!((top - Point2::new(0.25, 0.25)).norm() < 1e-12, "{}", top);
+        assert!((inner - Point2::new(0.5, 0.5)).norm() < 1e-12, "{}", inner);
+    }
+}
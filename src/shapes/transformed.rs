@@ -0,0 +1,264 @@
+use std::sync::Arc;
+
+use crate::geometry::{BoundingBox, Line3d};
+use crate::radiance::Radiance;
+use crate::shapes::{Material, Shape};
+
+use nalgebra::{Isometry3, Matrix3, Matrix4, Point2, Point3, Unit, Vector3, U3};
+
+/// Shape moved, rotated, scaled or sheared by an affine transformation. Rays
+/// are carried into the shape's own coordinates and its normals back out, so
+/// the shape keeps being defined as if untransformed.
+pub struct Transformed<S> {
+    shape: S,
+    to_world: Matrix4<f64>,
+    to_object: Matrix4<f64>,
+    /// Inverse transpose of the linear part, carrying normals to the world.
+    normal_matrix: Matrix3<f64>,
+    /// Factor by which the transformation scales volumes.
+    volume_scale: f64,
+}
+
+/// Transformed reference to geometry shared between many instances, such as
+/// a mesh loaded once and placed around the scene.
+pub type Instance = Transformed<Arc<dyn Shape>>;
+
+impl<S: Shape> Transformed<S> {
+    /// Places the shape with a homogeneous matrix from its coordinates to the
+    /// world's. Returns `None` if the matrix cannot be inverted.
+    pub fn new(shape: S, to_world: Matrix4<f64>) -> Option<Self> {
+        let to_object = to_world.try_inverse()?;
+        Some(Transformed {
+            shape,
+            to_world,
+            to_object,
+            normal_matrix: to_object.fixed_slice::<U3, U3>(0, 0).transpose(),
+            volume_scale: to_world.fixed_slice::<U3, U3>(0, 0).determinant().abs(),
+        })
+    }
+
+    /// Moves and rotates the shape without deforming it.
+    pub fn rigid(shape: S, isometry: &Isometry3<f64>) -> Self {
+        Transformed::new(shape, isometry.to_homogeneous()).expect("Isometries are invertible")
+    }
+
+    pub fn shape(&self) -> &S {
+        &self.shape
+    }
+
+    fn to_object_point(&self, position: &Point3<f64>) -> Point3<f64> {
+        self.to_object.transform_point(position)
+    }
+
+    fn to_world_normal(&self, normal: &Unit<Vector3<f64>>) -> Unit<Vector3<f64>> {
+        Unit::new_normalize(self.normal_matrix * normal.into_inner())
+    }
+
+    /// Converts the solid angle density of sampling a point, as seen in the
+    /// shape's coordinates, to the world's. Solid angles are only preserved
+    /// by similarities, so the density goes through the area density, which
+    /// scales with the surface's area.
+    fn to_world_pdf(&self, from: &Point3<f64>, position: &Point3<f64>, object_pdf: f64) -> f64 {
+        let object_from = self.to_object_point(from);
+        let object_position = self.to_object_point(position);
+        let object_normal = self.shape.normal_at(&object_position);
+        let world_normal = self.normal_matrix * object_normal.into_inner();
+        let area_scale = self.volume_scale * world_normal.norm();
+
+        let cos_ratio = |from: &Point3<f64>, position: &Point3<f64>, normal: &Vector3<f64>| {
+            let to_surface = position - from;
+            normal.normalize().dot(&to_surface).abs() / to_surface.norm().powi(3)
+        };
+        let object_factor = cos_ratio(&object_from, &object_position, &object_normal);
+        let world_factor = cos_ratio(from, position, &world_normal);
+        if world_factor <= 0.0 || area_scale <= 0.0 {
+            return 0.0;
+        }
+        object_pdf * object_factor / (area_scale * world_factor)
+    }
+}
+
+impl<S: Shape> Shape for Transformed<S> {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        let origin = self.to_object_point(&ray.origin());
+        let direction = self.to_object.transform_vector(&ray.direction());
+        let object_ray = Line3d::new(origin, Unit::new_normalize(direction));
+
+        self.shape
+            .intersect(&object_ray)
+            .into_iter()
+            .map(|(_, position)| (self as &dyn Shape, self.to_world.transform_point(&position)))
+            .collect()
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.shape.color_at(&self.to_object_point(position))
+    }
+
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        self.shape.uv_at(&self.to_object_point(position))
+    }
+
+    fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        self.to_world_normal(&self.shape.normal_at(&self.to_object_point(position)))
+    }
+
+    fn tangent_at(&self, position: &Point3<f64>) -> Vector3<f64> {
+        let tangent = self.shape.tangent_at(&self.to_object_point(position));
+        self.to_world.transform_vector(&tangent)
+    }
+
+    fn material(&self) -> &Material {
+        self.shape.material()
+    }
+
+    /// Textures stay attached to the shape as it moves.
+    fn local_position(&self, position: &Point3<f64>) -> Point3<f64> {
+        self.shape.local_position(&self.to_object_point(position))
+    }
+
+    fn sample_surface(&self, from: &Point3<f64>, random: (f64, f64)) -> Option<(Point3<f64>, f64)> {
+        let (object_position, object_pdf) =
+            self.shape.sample_surface(&self.to_object_point(from), random)?;
+        let position = self.to_world.transform_point(&object_position);
+        let pdf = self.to_world_pdf(from, &position, object_pdf);
+        if pdf > 0.0 {
+            Some((position, pdf))
+        } else {
+            None
+        }
+    }
+
+    fn surface_pdf(&self, from: &Point3<f64>, position: &Point3<f64>) -> f64 {
+        let object_pdf = self
+            .shape
+            .surface_pdf(&self.to_object_point(from), &self.to_object_point(position));
+        if object_pdf <= 0.0 {
+            return 0.0;
+        }
+        self.to_world_pdf(from, position, object_pdf)
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let bounds = self.shape.bounding_box()?;
+        let (min, max) = (bounds.min(), bounds.max());
+        let corners: Vec<_> = iproduct!(0..2, 0..2, 0..2)
+            .map(|(x, y, z)| {
+                let corner = Point3::new(
+                    if x == 0 { min.x } else { max.x },
+                    if y == 0 { min.y } else { max.y },
+                    if z == 0 { min.z } else { max.z },
+                );
+                self.to_world.transform_point(&corner)
+            })
+            .collect();
+        Some(BoundingBox::around(&corners))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::shapes::test_utils::distances;
+    use crate::shapes::{Cuboid, Rectangle, Sphere};
+    use nalgebra::Translation3;
+
+    fn unit_sphere() -> Sphere {
+        Sphere::new(Point3::origin(), 1.0, Color::gray(255))
+    }
+
+    #[test]
+    pub fn translated_sphere_moves() {
+        let isometry = Isometry3::from_parts(Translation3::new(0.0, 0.0, 5.0), Default::default());
+        let sphere = Transformed::rigid(unit_sphere(), &isometry);
+        let ray = Line3d::new(Point3::origin(), Vector3::z_axis());
+
+        let distances = distances(&sphere, &ray);
+
+        assert!((distances[0] - 4.0).abs() < 1e-12 && (distances[1] - 6.0).abs() < 1e-12);
+        let normal = sphere.normal_at(&Point3::new(0.0, 0.0, 4.0));
+        assert!((normal.into_inner() + Vector3::z()).norm() < 1e-12);
+    }
+
+    #[test]
+    pub fn stretched_sphere_normals_follow_ellipsoid() {
+        let matrix = Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 1.0, 1.0));
+        let ellipsoid = Transformed::new(unit_sphere(), matrix).unwrap();
+        let ray = Line3d::new(Point3::new(-5.0, 0.0, 0.0), Vector3::x_axis());
+
+        let distances = distances(&ellipsoid, &ray);
+        let normal = ellipsoid.normal_at(&Point3::new(2.0f64.sqrt(), 0.5f64.sqrt(), 0.0));
+
+        assert!((distances[0] - 3.0).abs() < 1e-12 && (distances[1] - 7.0).abs() < 1e-12);
+        // Gradient of x² / 4 + y² = 1.
+        let expected = Vector3::new(2.0f64.sqrt() / 4.0, 0.5f64.sqrt(), 0.0).normalize();
+        assert!((normal.into_inner() - expected).norm() < 1e-12, "{}", normal.into_inner());
+    }
+
+    #[test]
+    pub fn rotated_box_bounds() {
+        let corner = Point3::new(0.5, 0.5, 0.5);
+        let cube = Cuboid::new(-corner, corner, Color::gray(255));
+        let rotation = Isometry3::rotation(Vector3::new(0.0, 0.0, std::f64::consts::FRAC_PI_4));
+        let rotated = Transformed::rigid(cube, &rotation);
+
+        let bounds = rotated.bounding_box().unwrap();
+
+        let half_diagonal = 0.5f64.sqrt();
+        assert!((bounds.max() - Point3::new(half_diagonal, half_diagonal, 0.5)).norm() < 1e-12);
+    }
+
+    #[test]
+    pub fn singular_matrix_is_rejected() {
+        assert!(Transformed::new(unit_sphere(), Matrix4::zeros()).is_none());
+    }
+
+    #[test]
+    pub fn instances_share_geometry() {
+        let geometry: Arc<dyn Shape> = Arc::new(unit_sphere());
+        let instances: Vec<Instance> = (0..3)
+            .map(|i| {
+                let matrix = Matrix4::new_translation(&Vector3::new(3.0 * i as f64, 0.0, 0.0));
+                Instance::new(geometry.clone(), matrix).unwrap()
+            })
+            .collect();
+        let ray = Line3d::new(Point3::new(6.0, 0.0, -5.0), Vector3::z_axis());
+
+        assert_eq!(Arc::strong_count(&geometry), 4);
+        assert!(instances[1].intersect(&ray).is_empty());
+        assert_eq!(distances(&instances[2], &ray).len(), 2);
+    }
+
+    #[test]
+    pub fn sampling_density_follows_stretched_area() {
+        let square = Rectangle::new(Point3::origin(), Vector3::x(), Vector3::y(), Color::gray(255));
+        let matrix = Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 3.0, 1.0));
+        let stretched = Transformed::new(square, matrix).unwrap();
+        let expected = Rectangle::new(
+            Point3::origin(),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 3.0, 0.0),
+            Color::gray(255),
+        );
+        let from = Point3::new(-1.0, 2.0, 4.0);
+
+        let (position, pdf) = stretched.sample_surface(&from, (0.3, 0.8)).unwrap();
+
+        assert!((position - Point3::new(0.6, 2.4, 0.0)).norm() < 1e-12);
+        assert!((pdf - expected.surface_pdf(&from, &position)).abs() < 1e-9 * pdf);
+        assert!((pdf - stretched.surface_pdf(&from, &position)).abs() < 1e-9 * pdf);
+    }
+
+    #[test]
+    pub fn sampling_density_of_scaled_sphere() {
+        let scaled = Transformed::new(unit_sphere(), Matrix4::new_scaling(2.0)).unwrap();
+        let expected = Sphere::new(Point3::origin(), 2.0, Color::gray(255));
+        let from = Point3::new(0.0, 0.0, 5.0);
+
+        let (position, pdf) = scaled.sample_surface(&from, (0.4, 0.7)).unwrap();
+
+        assert!((position.coords.norm() - 2.0).abs() < 1e-9);
+        assert!((pdf - expected.surface_pdf(&from, &position)).abs() < 1e-9 * pdf);
+    }
+}
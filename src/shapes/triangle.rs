@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use crate::geometry::{BoundingBox, Line3d};
+use crate::radiance::Radiance;
+use crate::shapes::{Material, MeshData, Shape, Vertex};
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+const EPSILON: f64 = 1e-12;
+
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    vertices: [Vertex; 3],
+}
+
+impl Triangle {
+    pub fn new(mesh: Arc<MeshData>, vertices: [Vertex; 3]) -> Self {
+        Triangle { mesh, vertices }
+    }
+
+    fn position(&self, i: usize) -> Point3<f64> {
+        self.mesh.positions[self.vertices[i].position]
+    }
+
+    fn face_normal(&self) -> Unit<Vector3<f64>> {
+        let (a, b, c) = (self.position(0), self.position(1), self.position(2));
+        Unit::new_normalize((b - a).cross(&(c - a)))
+    }
+
+    fn barycentric(&self, position: &Point3<f64>) -> (f64, f64, f64) {
+        let a = self.position(0);
+        let ab = self.position(1) - a;
+        let ac = self.position(2) - a;
+        let ap = position - a;
+
+        let d00 = ab.dot(&ab);
+        let d01 = ab.dot(&ac);
+        let d11 = ac.dot(&ac);
+        let d20 = ap.dot(&ab);
+        let d21 = ap.dot(&ac);
+        let denominator = d00 * d11 - d01 * d01;
+
+        let v = (d11 * d20 - d01 * d21) / denominator;
+        let w = (d00 * d21 - d01 * d20) / denominator;
+        (1.0 - v - w, v, w)
+    }
+
+    /// Texture coordinates of the vertices, if all of them have some.
+    fn tex_coords(&self) -> Option<Vec<Point2<f64>>> {
+        self.vertices
+            .iter()
+            .map(|vertex| vertex.tex_coord.map(|index| self.mesh.tex_coords[index]))
+            .collect()
+    }
+
+    pub fn contains_projection_of(&self, position: &Point3<f64>) -> bool {
+        let (u, v, w) = self.barycentric(position);
+        u >= -1e-9 && v >= -1e-9 && w >= -1e-9
+    }
+
+    pub fn distance_to_plane(&self, position: &Point3<f64>) -> f64 {
+        (position - self.position(0)).dot(&self.face_normal()).abs()
+    }
+
+    pub fn area(&self) -> f64 {
+        let (a, b, c) = (self.position(0), self.position(1), self.position(2));
+        (b - a).cross(&(c - a)).norm() / 2.0
+    }
+
+    /// Point distributed uniformly over the triangle for uniform random
+    /// numbers.
+    pub fn point_at(&self, (u, v): (f64, f64)) -> Point3<f64> {
+        let root = u.sqrt();
+        let (b0, b1) = (1.0 - root, v * root);
+        let (a, b, c) = (self.position(0), self.position(1), self.position(2));
+        Point3::from(a.coords * b0 + b.coords * b1 + c.coords * (1.0 - b0 - b1))
+    }
+}
+
+impl Shape for Triangle {
+    fn intersect(&self, ray: &Line3d) -> Vec<(&dyn Shape, Point3<f64>)> {
+        // Möller–Trumbore
+        let a = self.position(0);
+        let ab = self.position(1) - a;
+        let ac = self.position(2) - a;
+
+        let p = ray.direction().cross(&ac);
+        let determinant = ab.dot(&p);
+        if determinant.abs() < EPSILON {
+            return Vec::new();
+        }
+
+        let s = ray.origin() - a;
+        let u = s.dot(&p) / determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return Vec::new();
+        }
+
+        let q = s.cross(&ab);
+        let v = ray.direction().dot(&q) / determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return Vec::new();
+        }
+
+        let d = ac.dot(&q) / determinant;
+        vec![(self as &dyn Shape, ray.at(d))]
+    }
+
+    fn color_at(&self, position: &Point3<f64>) -> Radiance {
+        self.textured_color(position, &self.mesh.color)
+    }
+
+    /// Interpolates the vertex texture coordinates. Triangles without them
+    /// are mapped onto the corners of the unit square.
+    fn uv_at(&self, position: &Point3<f64>) -> Point2<f64> {
+        let (u, v, w) = self.barycentric(position);
+        match self.tex_coords() {
+            Some(tex_coords) => Point2::from(
+                tex_coords[0].coords * u + tex_coords[1].coords * v + tex_coords[2].coords * w,
+            ),
+            None => Point2::new(v, w),
+        }
+    }
+
+    fn normal_at(&self, position: &Point3<f64>) -> Unit<Vector3<f64>> {
+        let normals = self.vertices.iter().map(|vertex| vertex.normal).collect::<Option<Vec<_>>>();
+
+        match normals {
+            Some(normals) => {
+                let (u, v, w) = self.barycentric(position);
+                Unit::new_normalize(
+                    self.mesh.normals[normals[0]].into_inner() * u
+                        + self.mesh.normals[normals[1]].into_inner() * v
+                        + self.mesh.normals[normals[2]].into_inner() * w,
+                )
+            }
+            None => self.face_normal(),
+        }
+    }
+
+    /// Follows the texture coordinates if the vertices have them, otherwise
+    /// the first edge.
+    fn tangent_at(&self, _: &Point3<f64>) -> Vector3<f64> {
+        let (a, b, c) = (self.position(0), self.position(1), self.position(2));
+        let (ab, ac) = (b - a, c - a);
+        if let Some(uv) = self.tex_coords() {
+            let (duv_ab, duv_ac) = (uv[1] - uv[0], uv[2] - uv[0]);
+            let determinant = duv_ab.x * duv_ac.y - duv_ac.x * duv_ab.y;
+            if determinant.abs() > EPSILON {
+                return (ab * duv_ac.y - ac * duv_ab.y) / determinant;
+            }
+        }
+        ab
+    }
+
+    /// Triangles are only sampled as part of their mesh, in proportion to
+    /// their area.
+    fn surface_pdf(&self, from: &Point3<f64>, position: &Point3<f64>) -> f64 {
+        let to_surface = position - from;
+        let distance2 = to_surface.norm_squared();
+        let cos_light = self.face_normal().dot(&to_surface).abs() / distance2.sqrt();
+        if cos_light <= 0.0 || self.mesh.area <= 0.0 {
+            return 0.0;
+        }
+        distance2 / (cos_light * self.mesh.area)
+    }
+
+    fn material(&self) -> &Material {
+        &self.mesh.material
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        Some(BoundingBox::around(&[
+            self.position(0),
+            self.position(1),
+            self.position(2),
+        ]))
+    }
+}
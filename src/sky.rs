@@ -0,0 +1,257 @@
+use std::f64::consts::PI;
+
+use crate::environment::EnvironmentMap;
+use crate::light::Light;
+use crate::radiance::Radiance;
+
+use nalgebra::{Unit, Vector3};
+
+/// Converts the sky luminance, in kcd/m², to the renderer's radiance units.
+const LUMINANCE_SCALE: f64 = 0.05;
+
+/// Coefficients of the Perez sky distribution for luminance and the two
+/// chromaticity coordinates, linear in turbidity.
+const PEREZ_Y: [(f64, f64); 5] = [
+    (0.1787, -1.4630),
+    (-0.3554, 0.4275),
+    (-0.0227, 5.3251),
+    (0.1206, -2.5771),
+    (-0.0670, 0.3703),
+];
+const PEREZ_X_CHROMATICITY: [(f64, f64); 5] = [
+    (-0.0193, -0.2592),
+    (-0.0665, 0.0008),
+    (-0.0004, 0.2125),
+    (-0.0641, -0.8989),
+    (-0.0033, 0.0452),
+];
+const PEREZ_Y_CHROMATICITY: [(f64, f64); 5] = [
+    (-0.0167, -0.2608),
+    (-0.0950, 0.0092),
+    (-0.0079, 0.2102),
+    (-0.0441, -1.6537),
+    (-0.0109, 0.0529),
+];
+
+/// Distribution of light over the sky relative to the zenith.
+struct Perez {
+    coefficients: [f64; 5],
+    /// Value at the zenith divided by the distribution there.
+    scale: f64,
+}
+
+impl Perez {
+    fn new(table: &[(f64, f64); 5], turbidity: f64, zenith_value: f64, sun_zenith: f64) -> Self {
+        let mut coefficients = [0.0; 5];
+        for (coefficient, (slope, offset)) in coefficients.iter_mut().zip(table) {
+            *coefficient = slope * turbidity + offset;
+        }
+
+        let mut perez = Perez {
+            coefficients,
+            scale: 1.0,
+        };
+        perez.scale = zenith_value / perez.distribution(1.0, sun_zenith);
+        perez
+    }
+
+    fn distribution(&self, cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = self.coefficients;
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+
+    fn value(&self, cos_theta: f64, gamma: f64) -> f64 {
+        self.scale * self.distribution(cos_theta, gamma)
+    }
+}
+
+/// Analytic clear sky after Preetham, Shirley and Smits, "A Practical
+/// Analytic Model for Daylight". The sun stands at the given elevation above
+/// the horizon and azimuth from +X towards +Y, both in radians, and
+/// `turbidity` describes the haze, from 2 for a very clear sky to about 10.
+pub struct Sky {
+    sun: Unit<Vector3<f64>>,
+    turbidity: f64,
+    luminance: Perez,
+    x: Perez,
+    y: Perez,
+}
+
+impl Sky {
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        let sun = Unit::new_normalize(Vector3::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+        ));
+        // The model is only defined for the sun above the horizon.
+        let sun_zenith = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0 - 0.01);
+        let t = turbidity;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * sun_zenith);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |t2: [f64; 3], t1: [f64; 4], t0: [f64; 4]| {
+            let (s, s2, s3) = (sun_zenith, sun_zenith.powi(2), sun_zenith.powi(3));
+            t * t * (t2[0] * s3 + t2[1] * s2 + t2[2] * s)
+                + t * (t1[0] * s3 + t1[1] * s2 + t1[2] * s + t1[3])
+                + (t0[0] * s3 + t0[1] * s2 + t0[2] * s + t0[3])
+        };
+        let zenith_x = chromaticity(
+            [0.00166, -0.00375, 0.00209],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        );
+        let zenith_y = chromaticity(
+            [0.00275, -0.00610, 0.00317],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        );
+
+        Sky {
+            sun,
+            turbidity,
+            luminance: Perez::new(&PEREZ_Y, t, zenith_luminance.max(0.0), sun_zenith),
+            x: Perez::new(&PEREZ_X_CHROMATICITY, t, zenith_x, sun_zenith),
+            y: Perez::new(&PEREZ_Y_CHROMATICITY, t, zenith_y, sun_zenith),
+        }
+    }
+
+    /// Direction from the scene towards the sun.
+    pub fn sun_direction(&self) -> Unit<Vector3<f64>> {
+        self.sun
+    }
+
+    /// Light arriving from the given direction. Below the horizon the sky is
+    /// black, as the ground is expected to be part of the scene.
+    pub fn radiance(&self, direction: &Vector3<f64>) -> Radiance {
+        let direction = direction.normalize();
+        if direction.z <= 0.0 {
+            return Radiance::black();
+        }
+
+        let cos_theta = direction.z.max(0.01);
+        let gamma = direction.dot(&self.sun).clamp(-1.0, 1.0).acos();
+        let luminance = self.luminance.value(cos_theta, gamma) * LUMINANCE_SCALE;
+        let x = self.x.value(cos_theta, gamma);
+        let y = self.y.value(cos_theta, gamma);
+
+        xyy_to_linear_srgb(x, y, luminance)
+    }
+
+    /// Fraction of sunlight reaching the ground in the red, green and blue
+    /// parts of the spectrum, after Rayleigh and aerosol scattering.
+    pub fn sun_color(&self) -> Radiance {
+        let elevation = self.sun.z.asin();
+        if elevation <= 0.0 {
+            return Radiance::black();
+        }
+
+        let zenith_degrees = 90.0 - elevation.to_degrees();
+        let air_mass = 1.0 / (elevation.sin() + 0.15 * (93.885 - zenith_degrees).powf(-1.253));
+        // Ångström's turbidity coefficient.
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |wavelength: f64| {
+            let rayleigh = 0.008735 * wavelength.powf(-4.08);
+            let aerosol = beta * wavelength.powf(-1.3);
+            (-air_mass * (rayleigh + aerosol)).exp()
+        };
+
+        Radiance::new(transmittance(0.65), transmittance(0.55), transmittance(0.45))
+    }
+
+    /// Directional light matching the position and color of the sun.
+    pub fn sun_light(&self, intensity: f64) -> Light {
+        Light::directional(-self.sun, intensity).with_color(self.sun_color())
+    }
+
+    /// Tabulates the sky into an equirectangular map, which lets renderers
+    /// importance sample it like any other environment.
+    pub fn to_environment(&self, width: usize, height: usize) -> EnvironmentMap {
+        let pixels = iproduct!(0..height, 0..width)
+            .map(|(y, x)| {
+                let phi = 2.0 * PI * (x as f64 + 0.5) / width as f64;
+                let theta = PI * (y as f64 + 0.5) / height as f64;
+                self.radiance(&Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                ))
+            })
+            .collect();
+
+        EnvironmentMap::new(width, height, pixels)
+    }
+}
+
+fn xyy_to_linear_srgb(x: f64, y: f64, luminance: f64) -> Radiance {
+    if y <= 0.0 {
+        return Radiance::black();
+    }
+
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Radiance::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::LightShape;
+
+    #[test]
+    pub fn sun_direction_from_angles() {
+        let sky = Sky::new(PI / 2.0, 0.0, 3.0);
+        assert!((sky.sun_direction().z - 1.0).abs() < 1e-12);
+
+        let sky = Sky::new(0.0, PI / 2.0, 3.0);
+        assert!((sky.sun_direction().y - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    pub fn clear_sky_is_blue() {
+        let sky = Sky::new(PI / 4.0, 0.0, 2.5);
+
+        let zenith = sky.radiance(&Vector3::z());
+
+        assert!(zenith.b > zenith.r, "{}", zenith);
+        assert!(zenith.luminance() > 0.0);
+    }
+
+    #[test]
+    pub fn brighter_towards_sun() {
+        let sky = Sky::new(0.3, 0.0, 3.0);
+
+        let near_sun = sky.radiance(&Vector3::new(1.0, 0.0, 0.35));
+        let opposite = sky.radiance(&Vector3::new(-1.0, 0.0, 0.35));
+
+        assert!(near_sun.luminance() > 2.0 * opposite.luminance());
+    }
+
+    #[test]
+    pub fn black_below_horizon() {
+        let sky = Sky::new(0.5, 0.0, 3.0);
+
+        assert_eq!(sky.radiance(&Vector3::new(1.0, 0.0, -0.1)), Radiance::black());
+    }
+
+    #[test]
+    pub fn low_sun_is_redder() {
+        let noon = Sky::new(1.2, 0.0, 3.0).sun_color();
+        let sunset = Sky::new(0.05, 0.0, 3.0).sun_color();
+
+        assert!(noon.r < 1.0 && noon.b > 0.5 * noon.r, "{}", noon);
+        assert!(sunset.b / sunset.r < noon.b / noon.r, "{} {}", sunset, noon);
+        assert_eq!(Sky::new(-0.1, 0.0, 3.0).sun_color(), Radiance::black());
+    }
+
+    #[test]
+    pub fn sun_light_shines_down() {
+        let light = Sky::new(0.5, 1.0, 3.0).sun_light(2.0);
+
+        assert!(matches!(light.shape, LightShape::Directional { direction } if direction.z < 0.0));
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use crate::sampling;
+use crate::textures::Texture;
+
+use nalgebra::{Point2, Point3, Unit, Vector3};
+
+/// Step used to estimate the gradient of height maps.
+const DELTA: f64 = 1e-3;
+
+/// Surface detail tilting the shading normal without changing the geometry.
+#[derive(Clone)]
+pub enum Bump {
+    /// Height given by the luminance of a texture, in units of length scaled
+    /// by `strength`. The normal follows the height field's gradient.
+    HeightMap {
+        texture: Arc<dyn Texture>,
+        strength: f64,
+    },
+    /// Tangent-space normals encoded as linear colors, with red along the
+    /// direction of increasing `u`, green along increasing `v` and blue away
+    /// from the surface.
+    NormalMap { texture: Arc<dyn Texture> },
+}
+
+impl Bump {
+    /// Perturbs the normal of a surface point given its tangent (the
+    /// direction of increasing `u`), texture coordinates and position
+    /// relative to the shape.
+    pub fn perturb(
+        &self,
+        normal: &Unit<Vector3<f64>>,
+        tangent: &Vector3<f64>,
+        uv: &Point2<f64>,
+        position: &Point3<f64>,
+    ) -> Unit<Vector3<f64>> {
+        let (tangent, bitangent) = Self::tangent_frame(normal, tangent);
+
+        match self {
+            Bump::HeightMap { texture, strength } => {
+                let height = |du: f64, dv: f64| {
+                    let uv = Point2::new(uv.x + du, uv.y + dv);
+                    let position = position + tangent * du + bitangent * dv;
+                    texture.color_at(&uv, &position).luminance()
+                };
+                let center = height(0.0, 0.0);
+                let slope_u = (height(DELTA, 0.0) - center) / DELTA * strength;
+                let slope_v = (height(0.0, DELTA) - center) / DELTA * strength;
+
+                Unit::new_normalize(normal.into_inner() - tangent * slope_u - bitangent * slope_v)
+            }
+            Bump::NormalMap { texture } => {
+                let color = texture.color_at(uv, position);
+                let local = Vector3::new(2.0 * color.r - 1.0, 2.0 * color.g - 1.0, 2.0 * color.b - 1.0);
+                if local.z <= 0.0 {
+                    return *normal;
+                }
+
+                Unit::new_normalize(tangent * local.x + bitangent * local.y + normal.into_inner() * local.z)
+            }
+        }
+    }
+
+    /// Orthonormal tangent and bitangent, with the tangent made perpendicular
+    /// to the normal.
+    fn tangent_frame(normal: &Unit<Vector3<f64>>, tangent: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        let tangent = tangent - normal.into_inner() * normal.dot(tangent);
+        match tangent.try_normalize(1e-12) {
+            Some(tangent) => (tangent, normal.cross(&tangent)),
+            None => sampling::orthonormal_basis(normal),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radiance::Radiance;
+
+    /// Texture returning the same color everywhere.
+    struct Constant(Radiance);
+
+    impl Texture for Constant {
+        fn color_at(&self, _: &Point2<f64>, _: &Point3<f64>) -> Radiance {
+            self.0
+        }
+    }
+
+    /// Height rising along `u`.
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn color_at(&self, uv: &Point2<f64>, _: &Point3<f64>) -> Radiance {
+            Radiance::gray(uv.x)
+        }
+    }
+
+    fn perturb(bump: &Bump) -> Unit<Vector3<f64>> {
+        bump.perturb(&Vector3::z_axis(), &Vector3::x(), &Point2::new(0.5, 0.5), &Point3::origin())
+    }
+
+    #[test]
+    pub fn flat_height_map_keeps_normal() {
+        let bump = Bump::HeightMap {
+            texture: Arc::new(Constant(Radiance::gray(0.3))),
+            strength: 1.0,
+        };
+
+        assert_eq!(perturb(&bump), Vector3::z_axis());
+    }
+
+    #[test]
+    pub fn height_map_tilts_down_the_slope() {
+        let bump = Bump::HeightMap {
+            texture: Arc::new(Ramp),
+            strength: 1.0,
+        };
+
+        let normal = perturb(&bump);
+
+        let expected = Vector3::new(-1.0, 0.0, 1.0).normalize();
+        assert!((normal.into_inner() - expected).norm() < 1e-6, "{}", normal.into_inner());
+    }
+
+    #[test]
+    pub fn neutral_normal_map_keeps_normal() {
+        let bump = Bump::NormalMap {
+            texture: Arc::new(Constant(Radiance::new(0.5, 0.5, 1.0))),
+        };
+
+        assert_eq!(perturb(&bump), Vector3::z_axis());
+    }
+
+    #[test]
+    pub fn normal_map_in_tangent_space() {
+        let bump = Bump::NormalMap {
+            texture: Arc::new(Constant(Radiance::new(0.5, 1.0, 1.0))),
+        };
+
+        let normal = bump.perturb(&Vector3::x_axis(), &Vector3::y(), &Point2::origin(), &Point3::origin());
+
+        // The bitangent of a frame with normal X and tangent Y is Z.
+        let expected = Vector3::new(1.0, 0.0, 1.0).normalize();
+        assert!((normal.into_inner() - expected).norm() < 1e-12, "{}", normal.into_inner());
+    }
+}
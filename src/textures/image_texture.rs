@@ -0,0 +1,255 @@
+use std::fs::File;
+use std::io;
+
+use crate::radiance::Radiance;
+use crate::textures::Texture;
+use crate::tone_mapping::decode_srgb;
+
+use nalgebra::{Point2, Point3};
+use png::{BitDepth, ColorType, Decoder, Transformations};
+
+/// How texture coordinates outside of `[0, 1]` are mapped onto the image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl WrapMode {
+    fn wrap(&self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::MirroredRepeat => {
+                let position = index.rem_euclid(2 * size);
+                if position < size {
+                    position
+                } else {
+                    2 * size - 1 - position
+                }
+            }
+            WrapMode::ClampToEdge => index.clamp(0, size - 1),
+        };
+        wrapped as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+/// Image mapped onto a shape through its texture coordinates, with `v`
+/// pointing up the image.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Radiance>,
+    wrap: WrapMode,
+    filter: Filter,
+    /// Number of repetitions of the image per unit of texture coordinates.
+    scale: (f64, f64),
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Radiance>) -> Self {
+        ImageTexture {
+            width,
+            height,
+            pixels,
+            wrap: WrapMode::Repeat,
+            filter: Filter::Bilinear,
+            scale: (1.0, 1.0),
+        }
+    }
+
+    /// Loads an sRGB encoded PNG image.
+    pub fn load(filename: &str) -> io::Result<Self> {
+        Self::read_png(filename, decode_srgb)
+    }
+
+    /// Loads a PNG image storing linear values, such as a normal map.
+    pub fn load_linear(filename: &str) -> io::Result<Self> {
+        Self::read_png(filename, |value| value)
+    }
+
+    fn read_png(filename: &str, decode: fn(f64) -> f64) -> io::Result<Self> {
+        let mut decoder = Decoder::new(File::open(filename)?);
+        decoder.set_transformations(Transformations::EXPAND);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data)?;
+
+        let (color_type, bit_depth) = reader.output_color_type();
+        let channels = match color_type {
+            ColorType::Grayscale => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::RGB => 3,
+            ColorType::RGBA => 4,
+            ColorType::Indexed => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "unexpanded palette"))
+            }
+        };
+        let bytes = if bit_depth == BitDepth::Sixteen { 2 } else { 1 };
+
+        // Only the most significant byte of 16-bit samples is used.
+        let component = |pixel: &[u8], channel: usize| {
+            decode(f64::from(pixel[channel * bytes]) / 255.0)
+        };
+        let pixels = data
+            .chunks_exact(channels * bytes)
+            .map(|pixel| {
+                if channels < 3 {
+                    Radiance::gray(component(pixel, 0))
+                } else {
+                    Radiance::new(component(pixel, 0), component(pixel, 1), component(pixel, 2))
+                }
+            })
+            .collect();
+
+        Ok(ImageTexture::new(info.width as usize, info.height as usize, pixels))
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_scale(mut self, u: f64, v: f64) -> Self {
+        self.scale = (u, v);
+        self
+    }
+
+    fn pixel(&self, x: i64, y: i64) -> Radiance {
+        let x = self.wrap.wrap(x, self.width);
+        let y = self.wrap.wrap(y, self.height);
+        self.pixels[y * self.width + x]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn color_at(&self, uv: &Point2<f64>, _: &Point3<f64>) -> Radiance {
+        let x = uv.x * self.scale.0 * self.width as f64;
+        let y = (1.0 - uv.y * self.scale.1) * self.height as f64;
+
+        match self.filter {
+            Filter::Nearest => self.pixel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                // Interpolate between the four nearest pixel centers.
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (left, top) = (x.floor(), y.floor());
+                let (fx, fy) = (x - left, y - top);
+                let (left, top) = (left as i64, top as i64);
+
+                let upper = self.pixel(left, top).mix(&self.pixel(left + 1, top), fx);
+                let lower = self.pixel(left, top + 1).mix(&self.pixel(left + 1, top + 1), fx);
+                upper.mix(&lower, fy)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use png::Encoder;
+    use std::io::BufWriter;
+
+    /// Black on the left, white on the right, one row.
+    fn gradient() -> ImageTexture {
+        ImageTexture::new(2, 1, vec![Radiance::black(), Radiance::gray(1.0)])
+    }
+
+    fn at(texture: &ImageTexture, u: f64, v: f64) -> Radiance {
+        texture.color_at(&Point2::new(u, v), &Point3::origin())
+    }
+
+    #[test]
+    pub fn wrap_modes() {
+        assert_eq!(WrapMode::Repeat.wrap(-1, 4), 3);
+        assert_eq!(WrapMode::Repeat.wrap(9, 4), 1);
+        assert_eq!(WrapMode::MirroredRepeat.wrap(-1, 4), 0);
+        assert_eq!(WrapMode::MirroredRepeat.wrap(5, 4), 2);
+        assert_eq!(WrapMode::ClampToEdge.wrap(-3, 4), 0);
+        assert_eq!(WrapMode::ClampToEdge.wrap(7, 4), 3);
+    }
+
+    #[test]
+    pub fn nearest_filter() {
+        let texture = gradient().with_filter(Filter::Nearest);
+
+        assert_eq!(at(&texture, 0.4, 0.5), Radiance::black());
+        assert_eq!(at(&texture, 0.6, 0.5), Radiance::gray(1.0));
+        assert_eq!(at(&texture, 1.6, 0.5), Radiance::gray(1.0));
+    }
+
+    #[test]
+    pub fn bilinear_filter() {
+        let texture = gradient().with_wrap(WrapMode::ClampToEdge);
+
+        assert_eq!(at(&texture, 0.25, 0.5), Radiance::black());
+        assert_eq!(at(&texture, 0.5, 0.5), Radiance::gray(0.5));
+        assert_eq!(at(&texture, 0.75, 0.5), Radiance::gray(1.0));
+        assert_eq!(at(&texture, 1.0, 0.5), Radiance::gray(1.0));
+    }
+
+    #[test]
+    pub fn bilinear_filter_wraps_around() {
+        let texture = gradient();
+
+        assert_eq!(at(&texture, 0.0, 0.5), Radiance::gray(0.5));
+    }
+
+    #[test]
+    pub fn v_points_up() {
+        let texture = ImageTexture::new(1, 2, vec![Radiance::gray(1.0), Radiance::black()])
+            .with_filter(Filter::Nearest);
+
+        assert_eq!(at(&texture, 0.5, 0.75), Radiance::gray(1.0));
+        assert_eq!(at(&texture, 0.5, 0.25), Radiance::black());
+    }
+
+    #[test]
+    pub fn load_png() {
+        let filename = std::env::temp_dir().join("ray_tracer_texture_test.png");
+        {
+            let file = File::create(&filename).unwrap();
+            let mut encoder = Encoder::new(BufWriter::new(file), 2, 1);
+            encoder.set_color(ColorType::RGBA);
+            encoder.set_depth(BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[255, 0, 0, 255, 0, 0, 255, 255]).unwrap();
+        }
+
+        let texture = ImageTexture::load(&filename.to_string_lossy())
+            .unwrap()
+            .with_filter(Filter::Nearest);
+
+        assert_eq!(at(&texture, 0.25, 0.5), Radiance::new(1.0, 0.0, 0.0));
+        assert_eq!(at(&texture, 0.75, 0.5), Radiance::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    pub fn load_linear_png() {
+        let filename = std::env::temp_dir().join("ray_tracer_linear_texture_test.png");
+        {
+            let file = File::create(&filename).unwrap();
+            let mut encoder = Encoder::new(BufWriter::new(file), 1, 1);
+            encoder.set_color(ColorType::RGB);
+            encoder.set_depth(BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0, 51, 255]).unwrap();
+        }
+
+        let texture = ImageTexture::load_linear(&filename.to_string_lossy()).unwrap();
+
+        assert_eq!(at(&texture, 0.5, 0.5), Radiance::new(0.0, 0.2, 1.0));
+    }
+}
@@ -0,0 +1,11 @@
+pub use self::bump::Bump;
+pub use self::image_texture::{Filter, ImageTexture, WrapMode};
+pub use self::noise::Noise;
+pub use self::procedural::{Checker, NoisePattern, NoiseTexture};
+pub use self::texture::Texture;
+
+mod bump;
+mod image_texture;
+mod noise;
+mod procedural;
+mod texture;
@@ -0,0 +1,180 @@
+use nalgebra::{Point3, Vector3};
+
+/// Octaves of fractal noise are twice as frequent and half as strong as the
+/// previous one.
+const LACUNARITY: f64 = 2.0;
+const GAIN: f64 = 0.5;
+
+const GRADIENTS: [(f64, f64, f64); 12] = [
+    (1.0, 1.0, 0.0),
+    (-1.0, 1.0, 0.0),
+    (1.0, -1.0, 0.0),
+    (-1.0, -1.0, 0.0),
+    (1.0, 0.0, 1.0),
+    (-1.0, 0.0, 1.0),
+    (1.0, 0.0, -1.0),
+    (-1.0, 0.0, -1.0),
+    (0.0, 1.0, 1.0),
+    (0.0, -1.0, 1.0),
+    (0.0, 1.0, -1.0),
+    (0.0, -1.0, -1.0),
+];
+
+/// Deterministic 3D noise functions. Different seeds give unrelated patterns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Noise {
+    seed: u64,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        Noise { seed }
+    }
+
+    /// Pseudo-random bits for an integer lattice point.
+    fn hash(&self, x: i64, y: i64, z: i64, salt: u64) -> u64 {
+        let mut hash = self.seed ^ salt.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        for coordinate in &[x, y, z] {
+            hash ^= *coordinate as u64;
+            // SplitMix64 finalizer
+            hash = hash.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            hash ^= hash >> 31;
+        }
+        hash
+    }
+
+    fn gradient(&self, x: i64, y: i64, z: i64) -> Vector3<f64> {
+        let (gx, gy, gz) = GRADIENTS[(self.hash(x, y, z, 0) % 12) as usize];
+        Vector3::new(gx, gy, gz)
+    }
+
+    /// Perlin's improved gradient noise, roughly within `[-1, 1]` and zero at
+    /// every integer lattice point.
+    pub fn perlin(&self, point: &Point3<f64>) -> f64 {
+        let cell = point.map(f64::floor);
+        let offset = point - cell;
+        let fade = offset.map(|t| t * t * t * (t * (t * 6.0 - 15.0) + 10.0));
+        let (x, y, z) = (cell.x as i64, cell.y as i64, cell.z as i64);
+
+        let corner = |dx: i64, dy: i64, dz: i64| {
+            let to_point = offset - Vector3::new(dx as f64, dy as f64, dz as f64);
+            self.gradient(x + dx, y + dy, z + dz).dot(&to_point)
+        };
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), fade.x);
+        let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), fade.x);
+        let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), fade.x);
+        let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), fade.x);
+        lerp(lerp(x00, x10, fade.y), lerp(x01, x11, fade.y), fade.z)
+    }
+
+    /// Fractional Brownian motion: octaves of Perlin noise, normalized to
+    /// roughly `[-1, 1]`.
+    pub fn fbm(&self, point: &Point3<f64>, octaves: usize) -> f64 {
+        self.octaves(point, octaves, |noise| noise)
+    }
+
+    /// Sum of the absolute values of noise octaves, within `[0, 1]`.
+    pub fn turbulence(&self, point: &Point3<f64>, octaves: usize) -> f64 {
+        self.octaves(point, octaves, f64::abs).min(1.0)
+    }
+
+    fn octaves(&self, point: &Point3<f64>, octaves: usize, shape: impl Fn(f64) -> f64) -> f64 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * shape(self.perlin(&(point * frequency)));
+            total += amplitude;
+            amplitude *= GAIN;
+            frequency *= LACUNARITY;
+        }
+        sum / total
+    }
+
+    /// Worley's cellular noise: distance to the nearest of the feature points
+    /// scattered one per unit cell.
+    pub fn worley(&self, point: &Point3<f64>) -> f64 {
+        let cell = point.map(f64::floor);
+        let (x, y, z) = (cell.x as i64, cell.y as i64, cell.z as i64);
+        let unit = |bits: u64| (bits >> 11) as f64 / (1u64 << 53) as f64;
+
+        iproduct!(-1..=1, -1..=1, -1..=1)
+            .map(|(dx, dy, dz)| {
+                let (cx, cy, cz) = (x + dx, y + dy, z + dz);
+                let feature = Point3::new(
+                    cx as f64 + unit(self.hash(cx, cy, cz, 1)),
+                    cy as f64 + unit(self.hash(cx, cy, cz, 2)),
+                    cz as f64 + unit(self.hash(cx, cy, cz, 3)),
+                );
+                (feature - point).norm()
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> impl Iterator<Item = Point3<f64>> {
+        iproduct!(0..20, 0..20, 0..5).map(|(x, y, z)| {
+            Point3::new(x as f64 * 0.37 - 3.0, y as f64 * 0.29 - 2.0, z as f64 * 0.53)
+        })
+    }
+
+    #[test]
+    pub fn perlin_vanishes_on_lattice() {
+        let noise = Noise::new(7);
+
+        for (x, y, z) in iproduct!(-2..2, -2..2, -2..2) {
+            assert_eq!(noise.perlin(&Point3::new(x as f64, y as f64, z as f64)), 0.0);
+        }
+    }
+
+    #[test]
+    pub fn perlin_is_bounded_and_varies() {
+        let noise = Noise::new(1);
+
+        let values: Vec<f64> = grid().map(|point| noise.perlin(&point)).collect();
+
+        assert!(values.iter().all(|value| value.abs() <= 1.0));
+        assert!(values.iter().any(|&value| value > 0.2));
+        assert!(values.iter().any(|&value| value < -0.2));
+    }
+
+    #[test]
+    pub fn seeds_give_different_noise() {
+        let point = Point3::new(0.3, 0.6, 0.2);
+
+        assert_eq!(Noise::new(1).perlin(&point), Noise::new(1).perlin(&point));
+        assert_ne!(Noise::new(1).perlin(&point), Noise::new(2).perlin(&point));
+    }
+
+    #[test]
+    pub fn fractal_noise_is_bounded() {
+        let noise = Noise::new(3);
+
+        for point in grid() {
+            assert!(noise.fbm(&point, 6).abs() <= 1.0);
+            assert!((0.0..=1.0).contains(&noise.turbulence(&point, 6)));
+        }
+    }
+
+    #[test]
+    pub fn worley_is_continuous_distance() {
+        let noise = Noise::new(4);
+
+        for point in grid() {
+            let distance = noise.worley(&point);
+            let nearby = noise.worley(&(point + Vector3::new(1e-3, 0.0, 0.0)));
+
+            assert!((0.0..3.0f64.sqrt()).contains(&distance));
+            assert!((distance - nearby).abs() <= 1.001e-3);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::color::Color;
+use crate::radiance::Radiance;
+use crate::textures::{Noise, Texture};
+
+use nalgebra::{Point2, Point3};
+
+/// Alternating cubes of two colors.
+pub struct Checker {
+    even: Radiance,
+    odd: Radiance,
+    /// Edge length of a cube.
+    size: f64,
+}
+
+impl Checker {
+    pub fn new(even: Radiance, odd: Radiance) -> Self {
+        Checker {
+            even,
+            odd,
+            size: 1.0,
+        }
+    }
+
+    /// The gray pattern of the default checkerboard floor.
+    pub fn board() -> Self {
+        Checker::new(Radiance::from(Color::gray(128)), Radiance::from(Color::gray(100)))
+    }
+
+    pub fn with_size(mut self, size: f64) -> Self {
+        self.size = size;
+        self
+    }
+}
+
+impl Texture for Checker {
+    fn color_at(&self, _: &Point2<f64>, position: &Point3<f64>) -> Radiance {
+        let cell = position / self.size;
+        if ((cell.x.floor() + cell.y.floor() + cell.z.floor()).abs() as i64) % 2 == 1 {
+            self.odd
+        } else {
+            self.even
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoisePattern {
+    /// Smooth clouds of fractional Brownian motion.
+    Fbm,
+    /// Billowy noise with sharp creases.
+    Turbulence,
+    /// Veins along the X axis distorted by turbulence.
+    Marble,
+    /// Rings around the Z axis.
+    Wood,
+    /// Cells growing darker towards their centers.
+    Worley,
+}
+
+/// Procedural pattern blending between two colors, evaluated at the position
+/// relative to the shape so that it needs no texture coordinates.
+pub struct NoiseTexture {
+    pattern: NoisePattern,
+    colors: (Radiance, Radiance),
+    /// Spatial frequency of the pattern.
+    scale: f64,
+    octaves: usize,
+    noise: Noise,
+}
+
+impl NoiseTexture {
+    pub fn new(pattern: NoisePattern, first: Radiance, second: Radiance) -> Self {
+        NoiseTexture {
+            pattern,
+            colors: (first, second),
+            scale: 1.0,
+            octaves: 6,
+            noise: Noise::new(0),
+        }
+    }
+
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.noise = Noise::new(seed);
+        self
+    }
+
+    /// Blend factor between the two colors.
+    fn value(&self, position: &Point3<f64>) -> f64 {
+        let point = position * self.scale;
+        match self.pattern {
+            NoisePattern::Fbm => 0.5 + 0.5 * self.noise.fbm(&point, self.octaves),
+            NoisePattern::Turbulence => self.noise.turbulence(&point, self.octaves),
+            NoisePattern::Marble => {
+                let phase = point.x + 5.0 * self.noise.turbulence(&point, self.octaves);
+                0.5 + 0.5 * phase.sin()
+            }
+            NoisePattern::Wood => {
+                let radius = (point.x * point.x + point.y * point.y).sqrt();
+                let rings = 4.0 * (radius + 0.3 * self.noise.fbm(&point, self.octaves));
+                // Sharp edge at the end of each yearly ring.
+                (rings - rings.floor()).powi(3)
+            }
+            NoisePattern::Worley => self.noise.worley(&point),
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn color_at(&self, _: &Point2<f64>, position: &Point3<f64>) -> Radiance {
+        let (first, second) = self.colors;
+        first.mix(&second, self.value(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(texture: &dyn Texture, x: f64, y: f64, z: f64) -> Radiance {
+        texture.color_at(&Point2::origin(), &Point3::new(x, y, z))
+    }
+
+    #[test]
+    pub fn checker_alternates_in_three_dimensions() {
+        let checker = Checker::new(Radiance::gray(1.0), Radiance::black()).with_size(2.0);
+
+        assert_eq!(at(&checker, 0.5, 0.5, 0.5), Radiance::gray(1.0));
+        assert_eq!(at(&checker, 2.5, 0.5, 0.5), Radiance::black());
+        assert_eq!(at(&checker, 2.5, -0.5, 0.5), Radiance::gray(1.0));
+        assert_eq!(at(&checker, 2.5, -0.5, -0.5), Radiance::black());
+    }
+
+    #[test]
+    pub fn noise_blends_between_colors() {
+        let first = Radiance::new(1.0, 0.0, 0.0);
+        let second = Radiance::new(0.0, 0.0, 1.0);
+        let patterns = [
+            NoisePattern::Fbm,
+            NoisePattern::Turbulence,
+            NoisePattern::Marble,
+            NoisePattern::Wood,
+            NoisePattern::Worley,
+        ];
+
+        for &pattern in &patterns {
+            let texture = NoiseTexture::new(pattern, first, second).with_scale(3.0);
+            let colors: Vec<Radiance> = iproduct!(0..10, 0..10)
+                .map(|(x, y)| at(&texture, x as f64 * 0.13, y as f64 * 0.17, 0.3))
+                .collect();
+
+            for color in &colors {
+                assert!((color.r + color.b - 1.0).abs() < 1e-9, "{:?} {}", pattern, color);
+            }
+            assert!(colors.iter().any(|color| (color.r - colors[0].r).abs() > 0.05), "{:?}", pattern);
+        }
+    }
+}
@@ -0,0 +1,9 @@
+use crate::radiance::Radiance;
+
+use nalgebra::{Point2, Point3};
+
+pub trait Texture: Send + Sync {
+    /// Color at a surface point, given its texture coordinates and its
+    /// position relative to the shape.
+    fn color_at(&self, uv: &Point2<f64>, position: &Point3<f64>) -> Radiance;
+}
@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use crate::color::Color;
+use crate::radiance::Radiance;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapping {
+    /// Clips everything brighter than 1.
+    Clamp,
+    /// `L / (1 + L)` applied to luminance; never reaches white.
+    Reinhard,
+    /// Reinhard with luminance `white` and above mapped to white.
+    ExtendedReinhard { white: f64 },
+    /// Narkowicz's fit of the ACES filmic curve, applied per channel.
+    Aces,
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "clamp" => Ok(ToneMapping::Clamp),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "extended-reinhard" => Ok(ToneMapping::ExtendedReinhard { white: 4.0 }),
+            "aces" => Ok(ToneMapping::Aces),
+            _ => Err(format!("Unknown tone mapping operator '{}'", name)),
+        }
+    }
+}
+
+impl ToneMapping {
+    fn apply(&self, radiance: Radiance) -> Radiance {
+        match *self {
+            ToneMapping::Clamp => radiance,
+            ToneMapping::Reinhard => {
+                Self::scale_luminance(radiance, |luminance| luminance / (1.0 + luminance))
+            }
+            ToneMapping::ExtendedReinhard { white } => Self::scale_luminance(radiance, |luminance| {
+                luminance * (1.0 + luminance / white.powi(2)) / (1.0 + luminance)
+            }),
+            ToneMapping::Aces => {
+                let curve = |x: f64| {
+                    let x = x.max(0.0);
+                    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+                };
+                Radiance::new(curve(radiance.r), curve(radiance.g), curve(radiance.b))
+            }
+        }
+    }
+
+    fn scale_luminance(radiance: Radiance, curve: impl Fn(f64) -> f64) -> Radiance {
+        let luminance = radiance.luminance();
+        if luminance <= 0.0 {
+            return Radiance::black();
+        }
+        radiance * (curve(luminance) / luminance)
+    }
+}
+
+/// Turns linear radiance into displayable 8-bit sRGB colors.
+pub struct ToneMapper {
+    operator: ToneMapping,
+    exposure: f64,
+}
+
+impl ToneMapper {
+    /// `exposure` is given in stops: each one doubles the brightness.
+    pub fn new(operator: ToneMapping, exposure: f64) -> Self {
+        ToneMapper { operator, exposure }
+    }
+
+    pub fn to_color(&self, radiance: &Radiance) -> Color {
+        let exposed = *radiance * 2.0f64.powf(self.exposure);
+        let mapped = self.operator.apply(exposed);
+        let component = |value: f64| (encode_srgb(value.clamp(0.0, 1.0)) * 255.0).round() as u8;
+
+        Color::rgb(component(mapped.r), component(mapped.g), component(mapped.b))
+    }
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        ToneMapper::new(ToneMapping::Clamp, 0.0)
+    }
+}
+
+pub fn encode_srgb(linear: f64) -> f64 {
+    if linear <= 0.003_130_8 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn decode_srgb(encoded: f64) -> f64 {
+    if encoded <= 0.040_45 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels(color: Color) -> (u8, u8, u8) {
+        (color.r, color.g, color.b)
+    }
+
+    #[test]
+    pub fn srgb_round_trip() {
+        for value in 0..=255 {
+            let encoded = f64::from(value) / 255.0;
+
+            let result = (encode_srgb(decode_srgb(encoded)) * 255.0).round() as u8;
+
+            assert_eq!(result, value);
+        }
+    }
+
+    #[test]
+    pub fn clamp_round_trips_colors() {
+        let color = Color::rgb(0, 128, 255);
+
+        let result = ToneMapper::default().to_color(&Radiance::from(&color));
+
+        assert_eq!(channels(result), (0, 128, 255));
+    }
+
+    #[test]
+    pub fn clamp_clips_out_of_range() {
+        let result = ToneMapper::default().to_color(&Radiance::new(-1.0, 0.0, 7.0));
+
+        assert_eq!(channels(result), (0, 0, 255));
+    }
+
+    #[test]
+    pub fn exposure_doubles_per_stop() {
+        let mapper = ToneMapper::new(ToneMapping::Clamp, 1.0);
+
+        let result = mapper.to_color(&Radiance::gray(0.25));
+
+        assert_eq!(channels(result), channels(ToneMapper::default().to_color(&Radiance::gray(0.5))));
+    }
+
+    #[test]
+    pub fn extended_reinhard_maps_white_point_to_white() {
+        let mapper = ToneMapper::new(ToneMapping::ExtendedReinhard { white: 4.0 }, 0.0);
+
+        assert_eq!(channels(mapper.to_color(&Radiance::gray(4.0))), (255, 255, 255));
+        assert!(mapper.to_color(&Radiance::gray(2.0)).r < 255);
+    }
+
+    #[test]
+    pub fn operators_are_monotonic() {
+        for operator in &[ToneMapping::Reinhard, ToneMapping::Aces] {
+            let mapper = ToneMapper::new(*operator, 0.0);
+            let values: Vec<u8> = (0..20)
+                .map(|i| mapper.to_color(&Radiance::gray(f64::from(i) * 0.25)).r)
+                .collect();
+
+            assert!(values.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", operator);
+        }
+    }
+}